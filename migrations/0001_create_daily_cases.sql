@@ -0,0 +1,10 @@
+CREATE TABLE daily_cases (
+    -- In ISO 8601 format (YYYY-MM-DD), so that dates sort and compare as text.
+    date TEXT PRIMARY KEY NOT NULL,
+    positive INTEGER NOT NULL,
+    recovered INTEGER NOT NULL,
+    deaths INTEGER NOT NULL,
+    active INTEGER NOT NULL,
+    -- When the values of the day were last inserted or changed, in RFC 3339 format.
+    updated_at TEXT NOT NULL
+);
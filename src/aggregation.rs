@@ -0,0 +1,174 @@
+use chrono::{Datelike, Duration, NaiveDate};
+
+use crate::{
+    date_range::{checked_shift_months, Period},
+    types::{AggregatedCase, CaseComparison, CaseDeltas, CaseTotals, DailyCases, Delta},
+};
+
+/// Splits a date range into consecutive buckets of the same size, aligned to the start of the range.
+///
+/// E.g. `2021-06-15` up to `2021-08-10` with `10d` buckets starts with `2021-06-15` - `2021-06-24`,
+/// and ends with the partial `2021-08-04` - `2021-08-10` bucket.
+pub struct Buckets {
+    since: NaiveDate,
+    upto: NaiveDate,
+    /// `None` means the whole range is a single bucket.
+    size: Option<Period>,
+}
+
+impl Buckets {
+    pub fn new(since: NaiveDate, upto: NaiveDate, size: Option<Period>) -> Self {
+        Self { since, upto, size }
+    }
+
+    /// Index of the bucket `date` falls into, `date` must be within the range.
+    pub fn index_of(&self, date: NaiveDate) -> u32 {
+        let days_since = (date - self.since).num_days() as u32;
+
+        match self.size {
+            None => 0,
+            Some(Period::Days(days)) => days_since / days,
+            Some(Period::Weeks(weeks)) => days_since / weeks.saturating_mul(7),
+            Some(Period::Months(months)) => self.months_since(date) / months,
+            Some(Period::Years(years)) => self.months_since(date) / years.saturating_mul(12),
+        }
+    }
+
+    /// First and last day of the bucket with the given index, clamped to the range.
+    pub fn bounds(&self, index: u32) -> (NaiveDate, NaiveDate) {
+        // `None` when the bucket starts beyond the supported dates.
+        let start_of = |index: u32| match self.size {
+            None => Some(self.since),
+            Some(Period::Days(days)) => {
+                let days = index.checked_mul(days)?;
+                self.since.checked_add_signed(Duration::days(days as i64))
+            }
+            Some(Period::Weeks(weeks)) => {
+                let days = index.checked_mul(weeks)?.checked_mul(7)?;
+                self.since.checked_add_signed(Duration::days(days as i64))
+            }
+            Some(Period::Months(months)) => {
+                let months = i32::try_from(index.checked_mul(months)?).ok()?;
+                checked_shift_months(self.since, months)
+            }
+            Some(Period::Years(years)) => {
+                let months = index.checked_mul(years)?.checked_mul(12)?;
+                checked_shift_months(self.since, i32::try_from(months).ok()?)
+            }
+        };
+
+        let upto = match self.size.and(index.checked_add(1)).and_then(start_of) {
+            Some(next_start) => next_start.pred().min(self.upto),
+            None => self.upto,
+        };
+
+        // Buckets are only ever indexed by the dates within the range, so they start within it.
+        (start_of(index).unwrap_or(self.since), upto)
+    }
+
+    /// Number of whole months between the start of the range and `date`.
+    fn months_since(&self, date: NaiveDate) -> u32 {
+        let months = (date.year() * 12 + date.month0() as i32)
+            - (self.since.year() * 12 + self.since.month0() as i32);
+
+        if checked_shift_months(self.since, months).is_none_or(|shifted| shifted > date) {
+            (months - 1) as u32
+        } else {
+            months as u32
+        }
+    }
+}
+
+impl DailyCases {
+    /// Sum up the daily cases between `since` and `upto` (both inclusive) into buckets of the given size.
+    ///
+    /// An omitted boundary defaults to the earliest or the latest date in the data,
+    /// buckets without any daily case are left out.
+    #[tracing::instrument(skip_all, fields(days = self.0.len()))]
+    pub fn aggregate(
+        self,
+        since: Option<NaiveDate>,
+        upto: Option<NaiveDate>,
+        bucket_size: Option<Period>,
+    ) -> Vec<AggregatedCase> {
+        let daily_cases = self.within(since, upto);
+
+        let (since, upto) = match (
+            since.or(daily_cases.earliest_date()),
+            upto.or(daily_cases.latest_date()),
+        ) {
+            (Some(since), Some(upto)) => (since, upto),
+            _ => return Vec::new(),
+        };
+
+        let buckets = Buckets::new(since, upto, bucket_size);
+
+        daily_cases
+            .fold_by(|daily| buckets.index_of(daily.date()))
+            .into_iter()
+            .map(|(index, totals)| {
+                let (since, upto) = buckets.bounds(index);
+                AggregatedCase::new(since, upto, totals)
+            })
+            .collect()
+    }
+
+    /// Sum up the daily cases between `since` and `upto` (both inclusive) into a single record.
+    #[tracing::instrument(skip_all, fields(days = self.0.len()))]
+    pub fn total_between(&self, since: NaiveDate, upto: NaiveDate) -> AggregatedCase {
+        let totals = self
+            .0
+            .iter()
+            .filter(|daily| (since..=upto).contains(&daily.date()))
+            .fold(CaseTotals::default(), |mut totals, daily| {
+                totals.add(daily);
+                totals
+            });
+
+        AggregatedCase::new(since, upto, totals)
+    }
+}
+
+impl AggregatedCase {
+    pub fn new(since: NaiveDate, upto: NaiveDate, totals: CaseTotals) -> Self {
+        Self {
+            since: since.to_string(),
+            upto: upto.to_string(),
+            days: totals.days,
+            positive: totals.positive,
+            recovered: totals.recovered,
+            deaths: totals.deaths,
+            active: totals.active,
+        }
+    }
+}
+
+impl Delta {
+    pub fn new(baseline: i32, current: i32) -> Self {
+        let absolute = current - baseline;
+        let percentage = (baseline != 0)
+            .then(|| (absolute as f64 / baseline.abs() as f64 * 10_000.0).round() / 100.0);
+
+        Self {
+            absolute,
+            percentage,
+        }
+    }
+}
+
+impl CaseComparison {
+    pub fn new(current: AggregatedCase, baseline: AggregatedCase) -> Self {
+        let delta = CaseDeltas {
+            positive: Delta::new(baseline.positive, current.positive),
+            recovered: Delta::new(baseline.recovered, current.recovered),
+            deaths: Delta::new(baseline.deaths, current.deaths),
+            active: Delta::new(baseline.active, current.active),
+        };
+
+        Self {
+            current,
+            baseline,
+            delta,
+        }
+    }
+}
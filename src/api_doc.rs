@@ -1,11 +1,16 @@
 use crate::{
+    config::AuthScope,
     routes::{
-        daily,
+        admin, aggregate, compare, daily,
         health::{self, ServiceHealth, ServiceStatus},
         index::{self, CasesSummary},
-        monthly, yearly,
+        insights, monthly, quality, snapshots, yearly,
+    },
+    types::{
+        AggregatedCase, CaseComparison, CaseDeltas, DailyCase, DailyRevision, Delta,
+        FilledDailyCase, KeyUsage, MetricRecords, MonthlyCase, Peaks, QualityCounts, QualityFlag,
+        QualityFlagKind, QualityReport, Record, Records, Snapshot, SnapshotDiff, Wave, YearlyCase,
     },
-    types::{DailyCase, MonthlyCase, YearlyCase},
 };
 
 use utoipa::OpenApi;
@@ -15,15 +20,23 @@ use utoipa::OpenApi;
     handlers(
         index::daily_cases_summary,
         health::service_health,
+        quality::quality_report,
         yearly::all_years,
         yearly::specific_year,
         monthly::all_months,
         monthly::all_months_in_a_year,
         monthly::specific_month,
         daily::all_days,
+        daily::latest_day,
         daily::all_days_in_a_month,
         daily::all_days_in_a_year,
         daily::specific_day,
+        aggregate::aggregate,
+        compare::compare,
+        insights::peaks,
+        snapshots::all_snapshots,
+        snapshots::diff_snapshots,
+        admin::key_usage,
     ),
     components(
         CasesSummary,
@@ -31,7 +44,26 @@ use utoipa::OpenApi;
         ServiceStatus,
         YearlyCase,
         MonthlyCase,
-        DailyCase
+        DailyCase,
+        FilledDailyCase,
+        AggregatedCase,
+        CaseComparison,
+        CaseDeltas,
+        Delta,
+        Peaks,
+        MetricRecords,
+        Records,
+        Record,
+        Wave,
+        QualityReport,
+        QualityCounts,
+        QualityFlag,
+        QualityFlagKind,
+        Snapshot,
+        SnapshotDiff,
+        DailyRevision,
+        KeyUsage,
+        AuthScope
     )
 )]
 pub struct ApiDoc;
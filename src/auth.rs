@@ -0,0 +1,178 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+};
+
+use actix_web::{http::header::RETRY_AFTER, HttpResponse, ResponseError};
+use chrono::{Duration, NaiveDate, Utc};
+
+use crate::{
+    config::{ApiKeyConfig, AuthConfig, AuthScope, ConfigError},
+    types::KeyUsage,
+};
+
+#[derive(Debug, derive_more::Display)]
+pub enum AuthError {
+    #[display(fmt = "Missing API key, send it in the X-Api-Key header")]
+    MissingKey,
+    #[display(fmt = "Invalid API key")]
+    InvalidKey,
+    #[display(fmt = "Not allowed to access {}", "_0.as_str()")]
+    OutOfScope(AuthScope),
+    #[display(fmt = "Daily quota of {} requests exceeded", _0)]
+    QuotaExceeded(u64),
+}
+
+impl ResponseError for AuthError {
+    fn error_response(&self) -> HttpResponse {
+        let mut http_response = match self {
+            AuthError::MissingKey | AuthError::InvalidKey => HttpResponse::Unauthorized(),
+            AuthError::OutOfScope(_) => HttpResponse::Forbidden(),
+            AuthError::QuotaExceeded(_) => {
+                let mut http_response = HttpResponse::TooManyRequests();
+                http_response.insert_header((RETRY_AFTER, secs_until_tomorrow()));
+                http_response
+            }
+        };
+
+        http_response.body(self.to_string())
+    }
+}
+
+/// API keys, and how much they've been used since the server started.
+pub struct ApiKeys {
+    config: AuthConfig,
+    /// Keys by their value.
+    keys: HashMap<String, ApiKeyConfig>,
+    usage: Mutex<Usage>,
+}
+
+struct Usage {
+    /// Day the requests of `requests_today` were made.
+    day: NaiveDate,
+    /// Usage of the keys by their name.
+    by_name: BTreeMap<String, KeyUsage>,
+}
+
+impl ApiKeys {
+    pub fn from_config(config: &AuthConfig) -> Result<Self, ConfigError> {
+        let keys = config.load_keys()?;
+
+        let by_name = keys
+            .iter()
+            .map(|key| {
+                let usage = KeyUsage {
+                    name: key.name.clone(),
+                    scopes: key.scopes.clone(),
+                    daily_quota: key.daily_quota,
+                    requests_today: 0,
+                    total_requests: 0,
+                    rejected_requests: 0,
+                    last_used_at: None,
+                };
+                (key.name.clone(), usage)
+            })
+            .collect();
+
+        Ok(Self {
+            config: config.clone(),
+            keys: keys.into_iter().map(|key| (key.key.clone(), key)).collect(),
+            usage: Mutex::new(Usage {
+                day: Utc::today().naive_utc(),
+                by_name,
+            }),
+        })
+    }
+
+    pub fn config(&self) -> &AuthConfig {
+        &self.config
+    }
+
+    /// Whether `api_key` is one of the keys, as opposed to a made-up one.
+    pub fn contains(&self, api_key: &str) -> bool {
+        self.keys.contains_key(api_key)
+    }
+
+    /// Check that `api_key`, or an anonymous request when `None`, may access every scope of
+    /// `scopes`, and count the request against the key's quota.
+    pub fn authorize(&self, api_key: Option<&str>, scopes: &[AuthScope]) -> Result<(), AuthError> {
+        let api_key = match api_key {
+            Some(api_key) => api_key,
+            None if !self.config.allow_anonymous => return Err(AuthError::MissingKey),
+            None => {
+                return match scopes
+                    .iter()
+                    .find(|scope| !self.config.anonymous_scopes.contains(scope))
+                {
+                    Some(scope) => Err(AuthError::OutOfScope(*scope)),
+                    None => Ok(()),
+                };
+            }
+        };
+
+        let key = self.keys.get(api_key).ok_or(AuthError::InvalidKey)?;
+
+        let now = Utc::now();
+        let mut usage = self.usage.lock().unwrap_or_else(|err| err.into_inner());
+        if usage.day != now.date().naive_utc() {
+            usage.day = now.date().naive_utc();
+            for key_usage in usage.by_name.values_mut() {
+                key_usage.requests_today = 0;
+            }
+        }
+
+        let key_usage = usage
+            .by_name
+            .get_mut(&key.name)
+            .expect("Every key has its usage");
+        key_usage.last_used_at = Some(now.to_rfc3339());
+
+        let result = match scopes.iter().find(|scope| !key.scopes.contains(scope)) {
+            Some(scope) => Err(AuthError::OutOfScope(*scope)),
+            None => match key.daily_quota {
+                Some(quota) if key_usage.requests_today >= quota => {
+                    Err(AuthError::QuotaExceeded(quota))
+                }
+                _ => Ok(()),
+            },
+        };
+
+        match result {
+            Ok(()) => {
+                key_usage.requests_today += 1;
+                key_usage.total_requests += 1;
+            }
+            Err(_) => key_usage.rejected_requests += 1,
+        }
+
+        result
+    }
+
+    /// Usage of every key, sorted by name.
+    pub fn usage(&self) -> Vec<KeyUsage> {
+        let usage = self.usage.lock().unwrap_or_else(|err| err.into_inner());
+        let today = Utc::today().naive_utc();
+
+        usage
+            .by_name
+            .values()
+            .cloned()
+            .map(|key_usage| KeyUsage {
+                requests_today: if usage.day == today {
+                    key_usage.requests_today
+                } else {
+                    0
+                },
+                ..key_usage
+            })
+            .collect()
+    }
+}
+
+/// Seconds until the quotas are reset, at midnight UTC.
+fn secs_until_tomorrow() -> i64 {
+    let now = Utc::now();
+    let tomorrow = (now.date() + Duration::days(1)).and_hms(0, 0, 0);
+
+    (tomorrow - now).num_seconds().max(1)
+}
@@ -0,0 +1,233 @@
+use std::{
+    collections::BTreeSet,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use actix_web::http::header::{HeaderMap, ACCEPT, ACCEPT_ENCODING};
+use futures_util::{stream, StreamExt};
+use redis::aio::ConnectionManager;
+use reqwest::Client;
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+use crate::{
+    compression::ContentEncoding,
+    config::{self, ServerConfig},
+    response::ResponseFormat,
+    single_flight::{Flight, SingleFlight},
+    storage::SharedStorage,
+    types::{source_api::SourceAPIResponse, DailyCases},
+    utils,
+};
+
+/// Header of the requests the server sends itself to refresh its cached responses.
+pub const CACHE_REFRESH_HEADER: &str = "x-cache-refresh";
+
+/// Redis the responses are cached in, shared by the workers.
+pub struct RedisCache {
+    client: redis::Client,
+    /// Shared by every request, connected on the first one and reconnected when it drops.
+    connection: OnceCell<ConnectionManager>,
+}
+
+impl RedisCache {
+    pub fn new(client: redis::Client) -> Self {
+        Self {
+            client,
+            connection: OnceCell::new(),
+        }
+    }
+
+    /// Connection to send commands with, without blocking the worker.
+    pub async fn connection(&self) -> redis::RedisResult<ConnectionManager> {
+        self.connection
+            .get_or_try_init(|| ConnectionManager::new(self.client.clone()))
+            .await
+            .cloned()
+    }
+}
+
+/// Encodings the responses are warmed up in, clients negotiating any of them.
+const WARM_UP_ENCODINGS: [ContentEncoding; 4] = [
+    ContentEncoding::Brotli,
+    ContentEncoding::Zstd,
+    ContentEncoding::Gzip,
+    ContentEncoding::Identity,
+];
+
+/// Responses computed at once while warming up, so that the workers keep serving the clients.
+const WARM_UP_CONCURRENCY: usize = 4;
+
+/// Server whose cache is warmed up, only set once it's serving rather than running a command.
+static WARM_UP_SERVER: OnceLock<&'static ServerConfig> = OnceLock::new();
+
+static WARM_UPS: SingleFlight<(), ()> = SingleFlight::new();
+
+/// The server is on the same host, so it's either reachable right away or not running.
+const LOOPBACK_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+static LOOPBACK_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Client of the requests the server sends itself, apart from the source API's client whose
+/// timeouts are meant for a remote server.<br>
+/// Responses aren't timed out, since they're as slow as fetching the source API they may need.
+fn loopback_client() -> &'static Client {
+    LOOPBACK_CLIENT.get_or_init(|| {
+        Client::builder()
+            .connect_timeout(LOOPBACK_CONNECT_TIMEOUT)
+            // A proxy from the environment couldn't reach the server's local address.
+            .no_proxy()
+            .build()
+            .expect("Failed building the loopback HTTP client")
+    })
+}
+
+/// Generated on boot, so that clients can't pass their own requests off as refreshes.
+static REFRESH_TOKEN: OnceLock<String> = OnceLock::new();
+
+fn refresh_token() -> &'static str {
+    REFRESH_TOKEN.get_or_init(|| Uuid::new_v4().to_string())
+}
+
+/// Whether a request is a refresh sent by the server itself, which is neither authenticated nor
+/// rate limited, and always computed rather than served from the cache.
+pub fn is_refresh(headers: &HeaderMap) -> bool {
+    headers
+        .get(CACHE_REFRESH_HEADER)
+        .is_some_and(|token| token.as_bytes() == refresh_token().as_bytes())
+}
+
+/// Address the server listening on `server` is reachable at from the same host.
+fn local_addr(server: &ServerConfig) -> SocketAddr {
+    let ip = match server.bind_address.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        Ok(IpAddr::V6(ip)) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        Ok(ip) => ip,
+        Err(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+    };
+
+    SocketAddr::new(ip, server.port)
+}
+
+/// Request `path_and_query` in `format` and `encoding` from the server listening on `server`,
+/// so that its response is computed and cached again.
+pub async fn refresh(
+    server: &ServerConfig,
+    path_and_query: &str,
+    format: ResponseFormat,
+    encoding: ContentEncoding,
+) -> Result<(), String> {
+    let url = format!("http://{}{path_and_query}", local_addr(server));
+
+    let res = loopback_client()
+        .get(&url)
+        .header(ACCEPT.as_str(), format.content_type())
+        .header(ACCEPT_ENCODING.as_str(), encoding.as_str())
+        .header(CACHE_REFRESH_HEADER, refresh_token())
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|err| format!("Failed refreshing {path_and_query}: {err}"))?;
+
+    // Read to the end, so that the connection is reused by the next refresh.
+    res.bytes()
+        .await
+        .map(|_| ())
+        .map_err(|err| format!("Failed refreshing {path_and_query}: {err}"))
+}
+
+/// Paths of the common responses: the summary, every year, and the `recent_months` latest months
+/// of `daily_cases`.
+pub fn warm_up_paths(daily_cases: &DailyCases, recent_months: usize) -> Vec<String> {
+    let months = daily_cases
+        .0
+        .iter()
+        .map(|daily_case| (daily_case.year, daily_case.month))
+        .collect::<BTreeSet<_>>();
+    let years = months
+        .iter()
+        .map(|(year, _)| *year)
+        .collect::<BTreeSet<_>>();
+
+    let mut paths = ["/", "/daily", "/monthly", "/yearly"]
+        .map(String::from)
+        .to_vec();
+    for year in years {
+        paths.push(format!("/monthly/{year}"));
+        paths.push(format!("/daily/{year}"));
+    }
+    for (year, month) in months.iter().rev().take(recent_months) {
+        paths.push(format!("/monthly/{year}/{month}"));
+        paths.push(format!("/daily/{year}/{month}"));
+    }
+
+    paths
+}
+
+/// Cache the JSON responses of `paths` in every encoding, by refreshing them on the server
+/// listening on `server`.<br>
+/// Returns how many of them were cached.
+pub async fn warm_up(server: &ServerConfig, paths: &[String]) -> usize {
+    let refreshes = paths.iter().flat_map(|path| {
+        WARM_UP_ENCODINGS
+            .into_iter()
+            .map(move |encoding| refresh(server, path, ResponseFormat::Json, encoding))
+    });
+
+    stream::iter(refreshes)
+        .buffer_unordered(WARM_UP_CONCURRENCY)
+        .filter_map(|result| async move {
+            result
+                .map_err(|err| tracing::warn!(error = %err, "Failed warming up the cache"))
+                .ok()
+        })
+        .count()
+        .await
+}
+
+/// Warm up the cache of the server listening on `server` from now on: whenever the source API's
+/// data changes, starting with the data ingested into `storage` right away.
+pub fn enable_warm_up(server: &'static ServerConfig, storage: SharedStorage) {
+    WARM_UP_SERVER.get_or_init(|| server);
+
+    // The first data fetched is new, so ingesting it warms up the cache, and the requests of the
+    // warm-up are then served from the storage rather than fetching the source API again.
+    actix_web::rt::spawn(async move {
+        if let Err(err) = utils::ingest_into_storage(&storage).await {
+            tracing::warn!(error = %err, "Skipping the cache warm-up at boot");
+        }
+    });
+}
+
+/// Warm up the cache with the responses of `source`'s data, if it's enabled and not already being
+/// warmed up.<br>
+/// Only called when the source API's data changed, since the cached responses are up to date
+/// otherwise.
+pub fn warm_up_in_background(source: &SourceAPIResponse) {
+    let server = match WARM_UP_SERVER.get() {
+        Some(server) => *server,
+        None => return,
+    };
+    let leader = match WARM_UPS.join(()) {
+        Flight::Leader(leader) => leader,
+        Flight::Follower(_) => return,
+    };
+    let paths = warm_up_paths(
+        &source.to_daily(),
+        config::get().cache.warm_up_recent_months,
+    );
+
+    actix_web::rt::spawn(async move {
+        let started_at = Instant::now();
+        let warmed_up = warm_up(server, &paths).await;
+        tracing::info!(
+            warmed_up,
+            total = paths.len() * WARM_UP_ENCODINGS.len(),
+            duration_ms = started_at.elapsed().as_millis() as u64,
+            "Warmed up the cache"
+        );
+        leader.complete(());
+    });
+}
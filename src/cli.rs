@@ -0,0 +1,151 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use utoipa::OpenApi;
+
+use crate::{
+    api_doc::ApiDoc,
+    config::Config,
+    date_range::{DateBound, DateRange},
+    response::ResponseFormat,
+    storage::SharedStorage,
+    types::source_api::SourceAPIResponse,
+    utils,
+};
+
+/// Indonesia's COVID-19 data, served as a REST API.
+#[derive(Parser, Debug)]
+#[clap(version)]
+pub struct Cli {
+    /// Config file to use instead of `CONFIG_FILE` or `config.toml`.
+    #[clap(long, global = true)]
+    pub config: Option<String>,
+
+    /// What to do, the server is started if omitted.
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum Command {
+    /// Start the server.
+    Serve,
+    /// Download the source API's data to a file, after checking that it can be processed.
+    Fetch(FetchArgs),
+    /// Write the daily, monthly, or yearly cases as JSON or CSV.
+    Export(ExportArgs),
+    /// Validate the config and print the config in effect.
+    CheckConfig,
+    /// Print the OpenAPI spec of the server.
+    Openapi,
+}
+
+#[derive(Args, Debug, PartialEq)]
+pub struct FetchArgs {
+    /// File to write the data to, e.g. to be used as `upstream.source_file`.
+    #[clap(short, long, default_value = "update.json")]
+    pub output: String,
+}
+
+#[derive(Args, Debug, PartialEq)]
+pub struct ExportArgs {
+    #[clap(value_enum)]
+    pub granularity: Granularity,
+
+    #[clap(short, long, value_enum, default_value = "json")]
+    pub format: ExportFormat,
+
+    /// File to write the data to, the standard output if omitted.
+    #[clap(short, long)]
+    pub output: Option<String>,
+
+    /// Only the days since this date, YYYY-MM-DD or relative to the latest date in the data (today-N).
+    #[clap(long, value_parser = DateBound::parse)]
+    pub since: Option<DateBound>,
+
+    /// Only the days up to this date, YYYY-MM-DD or relative to the latest date in the data (today-N).
+    #[clap(long, value_parser = DateBound::parse)]
+    pub upto: Option<DateBound>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Daily,
+    Monthly,
+    Yearly,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+/// Download the source API's data, returning its raw body along with a short description of it.
+pub async fn fetch() -> Result<(Vec<u8>, String), String> {
+    let body = utils::download_from_source_api().await?;
+    let source = serde_json::from_slice::<SourceAPIResponse>(&body)
+        .map_err(|err| format!("Failed processing data: {err}"))?;
+
+    let daily_cases = source.to_daily();
+    let (earliest, latest) = daily_cases
+        .earliest_date()
+        .zip(daily_cases.latest_date())
+        .ok_or("The source API has no daily cases.")?;
+
+    let description = format!(
+        "{} days from {earliest} to {latest}, {} data-quality flag(s)",
+        daily_cases.0.len(),
+        daily_cases.quality_flags().len()
+    );
+
+    Ok((body, description))
+}
+
+/// Serialize the stored cases as requested by `args`.
+pub async fn export(args: &ExportArgs) -> Result<Vec<u8>, String> {
+    let storage = SharedStorage::open_from_config()?;
+    utils::ingest_into_storage(&storage).await?;
+    let range = DateRange {
+        since: args.since,
+        upto: args.upto,
+        last: None,
+    };
+
+    let granularity = args.granularity;
+    let daily_cases = storage
+        .query(move |storage| {
+            let latest_date = storage.latest_date()?;
+            let (since, upto) = match granularity {
+                Granularity::Monthly => range.resolve_months(latest_date),
+                _ => range.resolve(latest_date),
+            };
+            storage.daily_cases(since, upto)
+        })
+        .await?;
+
+    let format = match args.format {
+        ExportFormat::Json => ResponseFormat::Json,
+        ExportFormat::Csv => ResponseFormat::Csv,
+    };
+
+    match args.granularity {
+        Granularity::Daily => format.serialize(&daily_cases.0),
+        Granularity::Monthly => format.serialize(&daily_cases.to_monthly().0),
+        Granularity::Yearly => format.serialize(&daily_cases.to_yearly().0),
+    }
+}
+
+/// The config in effect, as TOML.
+pub fn check_config(path: Option<&str>) -> Result<String, String> {
+    let config = Config::load_from(path).map_err(|err| err.to_string())?;
+    // Through a `Value`, which orders the keys before the tables such as `[[auth.keys]]`.
+    toml::Value::try_from(&config)
+        .and_then(|config| toml::to_string(&config))
+        .map_err(|err| err.to_string())
+}
+
+pub fn openapi() -> Result<String, String> {
+    ApiDoc::openapi()
+        .to_pretty_json()
+        .map(|spec| spec + "\n")
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,94 @@
+use std::io::Write;
+
+use actix_web::http::header::{HeaderMap, ACCEPT_ENCODING};
+
+/// Content coding of an endpoint's response, negotiated from the `Accept-Encoding` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Brotli,
+    Zstd,
+    Gzip,
+    Identity,
+}
+
+impl ContentEncoding {
+    /// Ordered by our own preference, used to break ties between equally weighted codings.
+    const PREFERENCE: [Self; 4] = [Self::Brotli, Self::Zstd, Self::Gzip, Self::Identity];
+
+    /// Pick the coding with the highest quality value out of the given `Accept-Encoding` header,
+    /// falls back to `Identity` when the client doesn't accept any of the supported codings.
+    pub fn negotiate(headers: &HeaderMap) -> Self {
+        let accept_encoding = match headers
+            .get(ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+        {
+            Some(accept_encoding) => accept_encoding,
+            None => return Self::Identity,
+        };
+
+        let weighted = accept_encoding
+            .split(',')
+            .filter_map(|coding| {
+                let mut parts = coding.split(';').map(str::trim);
+                let name = parts.next()?.to_ascii_lowercase();
+                let quality = parts
+                    .find_map(|param| param.strip_prefix("q="))
+                    .map(|q| q.parse::<f32>().unwrap_or(0.0))
+                    .unwrap_or(1.0);
+
+                Some((name, quality))
+            })
+            .collect::<Vec<_>>();
+
+        let quality_of = |encoding: &Self| {
+            weighted
+                .iter()
+                .find(|(name, _)| name == encoding.as_str())
+                .or_else(|| weighted.iter().find(|(name, _)| name == "*"))
+                .map(|(_, quality)| *quality)
+        };
+
+        Self::PREFERENCE
+            .into_iter()
+            .filter(|encoding| *encoding != Self::Identity)
+            .filter_map(|encoding| Some((encoding, quality_of(&encoding)?)))
+            .filter(|(_, quality)| *quality > 0.0)
+            .fold(None, |best: Option<(Self, f32)>, candidate| match best {
+                Some(best) if best.1 >= candidate.1 => Some(best),
+                _ => Some(candidate),
+            })
+            .map(|(encoding, _)| encoding)
+            .unwrap_or(Self::Identity)
+    }
+
+    /// Token used in the `Content-Encoding` header, also a stable part of a cache key.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Zstd => "zstd",
+            Self::Gzip => "gzip",
+            Self::Identity => "identity",
+        }
+    }
+
+    pub fn compress(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let compressed = match self {
+            Self::Identity => Ok(data.to_owned()),
+            Self::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data).and_then(|_| encoder.finish())
+            }
+            Self::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+                encoder
+                    .write_all(data)
+                    .and_then(|_| encoder.flush())
+                    .map(|_| encoder.into_inner())
+            }
+            Self::Zstd => zstd::encode_all(data, 0),
+        };
+
+        compressed.map_err(|err| format!("Failed compressing response: {err}"))
+    }
+}
@@ -0,0 +1,790 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    net::IpAddr,
+    path::Path,
+    sync::OnceLock,
+    time::Duration,
+};
+
+use redis::IntoConnectionInfo;
+use reqwest::{header::HeaderName, Method, Url};
+use serde::{Deserialize, Serialize, Serializer};
+use toml::{value::Table, Value};
+use utoipa::Component;
+
+use crate::{snapshots::DEFAULT_SNAPSHOTS_DIR, storage::DEFAULT_DATABASE_PATH, utils};
+
+/// Config file read when `CONFIG_FILE` isn't set, it's fine for it not to exist.
+pub const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// Prefix of the environment variables overriding a key of the config file, with `__` between
+/// the section and the key, e.g. `COVID_API__CACHE__TTL_SECS` overrides `ttl_secs` of `[cache]`.
+pub const ENV_PREFIX: &str = "COVID_API__";
+
+/// Stands for the secrets of a serialized config, so that checking the config doesn't print them.
+pub const REDACTED: &str = "REDACTED";
+
+/// Environment variables that predate the config file, and the key each of them overrides.
+const ENV_ALIASES: &[(&str, &str)] = &[
+    ("PORT", "server.port"),
+    ("SOURCE_FILE", "upstream.source_file"),
+    ("DATABASE_PATH", "storage.database_path"),
+    ("SNAPSHOTS_DIR", "storage.snapshots_dir"),
+    ("REDIS_URL", "cache.redis_url"),
+];
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, derive_more::Display)]
+pub enum ConfigError {
+    #[display(fmt = "Failed reading config file {}: {}", _0, _1)]
+    Read(String, String),
+    #[display(fmt = "Malformed config: {}", _0)]
+    Malformed(String),
+    #[display(fmt = "Invalid config: {}", _0)]
+    Invalid(String),
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub upstream: UpstreamConfig,
+    pub storage: StorageConfig,
+    pub cache: CacheConfig,
+    pub cors: CorsConfig,
+    pub logging: LoggingConfig,
+    pub rate_limit: RateLimitConfig,
+    pub auth: AuthConfig,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// IP address the server listens on.
+    pub bind_address: String,
+    pub port: u16,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpstreamConfig {
+    /// URL of the source API's `update.json`.
+    pub url: String,
+    /// Path of a local `update.json` to read instead of the source API, e.g. to run offline.
+    pub source_file: Option<String>,
+    pub connect_timeout_secs: u64,
+    /// Timeout of a whole request to the source API, connecting included.
+    pub timeout_secs: u64,
+    /// Minimum time between two fetches of the source API while the storage holds data,
+    /// 0 fetches it on every request.
+    pub refresh_interval_secs: u64,
+    /// Retries of a fetch failing with a network error, a `429`, or a `5xx`.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every following one and randomized by up to half.
+    pub retry_backoff_ms: u64,
+    /// Consecutive failed fetches after which the source API isn't fetched for a while,
+    /// the latest snapshot being served instead.
+    pub circuit_breaker_threshold: u32,
+    pub circuit_breaker_cooldown_secs: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// SQLite database file, opened once at boot and shared by the workers.<br>
+    /// `:memory:` keeps the database in memory, so it starts empty on every boot.
+    pub database_path: String,
+    pub snapshots_dir: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheBackend {
+    Redis,
+    /// Responses aren't cached at all.
+    None,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub backend: CacheBackend,
+    #[serde(serialize_with = "serialize_redacted_url")]
+    pub redis_url: String,
+    /// How long a response is fresh, unless its path has a TTL of its own.
+    pub ttl_secs: u64,
+    /// How long a response keeps being served once it's no longer fresh, while it's refreshed
+    /// in the background, 0 expires it as soon as it's no longer fresh.
+    pub stale_ttl_secs: u64,
+    /// Cache the common responses at boot, and again whenever the source API's data changes,
+    /// rather than when they're first requested.
+    pub warm_up: bool,
+    /// Latest months whose responses are warmed up, along with the ones of every year.
+    pub warm_up_recent_months: usize,
+    /// TTLs of the responses whose path starts with the given prefix, the longest prefix wins.
+    pub route_ttl_secs: BTreeMap<String, u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins allowed to make cross-origin requests, e.g. `https://example.com`, or `*` for any.
+    /// Cross-origin requests are disallowed when empty.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    /// Request headers allowed besides the ones browsers always send, compared case-insensitively.
+    pub allowed_headers: Vec<String>,
+    /// Response headers readable by the browser besides the ones it always exposes.
+    pub exposed_headers: Vec<String>,
+    /// How long browsers may cache the result of a preflight request.
+    pub max_age_secs: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Off,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Overridden by `RUST_LOG` when it's set.
+    pub level: LogLevel,
+    pub format: LogFormat,
+    /// OTLP/gRPC endpoint of an OpenTelemetry collector to export the traces to,
+    /// e.g. `http://localhost:4317`. Needs the `otlp` feature.
+    pub otlp_endpoint: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitBackend {
+    /// Buckets are kept by each instance of the server.
+    Memory,
+    /// Buckets are kept in Redis at `cache.redis_url`, shared by every instance of the server.
+    Redis,
+}
+
+/// What a client is told apart by.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    Ip,
+    /// The `X-Api-Key` header, or the IP address for the requests without one.
+    ApiKey,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub backend: RateLimitBackend,
+    pub key: RateLimitKey,
+    /// Requests a client may make at once, i.e. the capacity of its token bucket.
+    pub burst: u32,
+    /// Rate its token bucket is refilled at.
+    pub requests_per_minute: u32,
+    /// Take the client's IP address from the `X-Forwarded-For` or `Forwarded` header,
+    /// only when the server is behind a proxy that sets it.
+    pub trust_forwarded_for: bool,
+    /// Paths that aren't rate limited, along with the paths under them.
+    pub exempt_paths: Vec<String>,
+}
+
+/// Route group an API key may access.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Component)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthScope {
+    /// The daily cases, along with the summary at `/` and everything computed from the daily
+    /// cases: `/aggregate`, `/compare`, `/insights`, `/quality`, and `/snapshots`.
+    Daily,
+    Monthly,
+    Yearly,
+    /// Responses in another format than JSON, e.g. CSV.
+    Exports,
+    /// The usage of the API keys, never granted to anonymous requests.
+    Admin,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    /// Name the key's usage is reported under, e.g. the organization it was given to.
+    pub name: String,
+    #[serde(serialize_with = "serialize_redacted")]
+    pub key: String,
+    #[serde(default)]
+    pub scopes: Vec<AuthScope>,
+    /// Requests the key may make per day (UTC), unlimited when unset.
+    #[serde(default)]
+    pub daily_quota: Option<u64>,
+}
+
+/// API keys of a `auth.keys_file`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiKeysFile {
+    pub keys: Vec<ApiKeyConfig>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub enabled: bool,
+    /// Let the requests without an `X-Api-Key` header through, within `anonymous_scopes`.
+    pub allow_anonymous: bool,
+    pub anonymous_scopes: Vec<AuthScope>,
+    pub keys: Vec<ApiKeyConfig>,
+    /// TOML file holding more `[[keys]]`, e.g. to keep them out of the config file.
+    pub keys_file: Option<String>,
+    /// Paths that don't need a key, along with the paths under them.
+    pub exempt_paths: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: "0.0.0.0".to_string(),
+            port: 8082,
+        }
+    }
+}
+
+impl Default for UpstreamConfig {
+    fn default() -> Self {
+        Self {
+            url: utils::COVID_API_ENDPOINT.to_string(),
+            source_file: None,
+            connect_timeout_secs: 5,
+            timeout_secs: 30,
+            refresh_interval_secs: 600,
+            max_retries: 2,
+            retry_backoff_ms: 250,
+            circuit_breaker_threshold: 5,
+            circuit_breaker_cooldown_secs: 60,
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            database_path: DEFAULT_DATABASE_PATH.to_string(),
+            snapshots_dir: DEFAULT_SNAPSHOTS_DIR.to_string(),
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            backend: CacheBackend::Redis,
+            redis_url: "redis://127.0.0.1/".to_string(),
+            ttl_secs: 600,
+            stale_ttl_secs: 3600,
+            warm_up: true,
+            warm_up_recent_months: 3,
+            route_ttl_secs: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: vec!["GET".to_string(), "HEAD".to_string()],
+            allowed_headers: vec![
+                "Accept".to_string(),
+                "Accept-Encoding".to_string(),
+                "X-Api-Key".to_string(),
+                "X-Request-Id".to_string(),
+            ],
+            exposed_headers: vec![
+                "X-Request-Id".to_string(),
+                "RateLimit-Limit".to_string(),
+                "RateLimit-Remaining".to_string(),
+                "RateLimit-Reset".to_string(),
+                "Retry-After".to_string(),
+                "Age".to_string(),
+                "Cache-Status".to_string(),
+            ],
+            max_age_secs: 3600,
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+            format: LogFormat::Text,
+            otlp_endpoint: None,
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            backend: RateLimitBackend::Memory,
+            key: RateLimitKey::Ip,
+            burst: 60,
+            requests_per_minute: 60,
+            trust_forwarded_for: false,
+            exempt_paths: vec!["/health".to_string(), "/metrics".to_string()],
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file given by `CONFIG_FILE`, or `config.toml` if it exists,
+    /// override it with the environment variables, and validate the result.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::load_from(std::env::var("CONFIG_FILE").ok().as_deref())
+    }
+
+    /// Same as [`Config::load`], but with the config file at `path` if given.
+    pub fn load_from(path: Option<&str>) -> Result<Self, ConfigError> {
+        let contents = match path {
+            Some(path) => Some(
+                fs::read_to_string(path)
+                    .map_err(|err| ConfigError::Read(path.to_string(), err.to_string()))?,
+            ),
+            None if Path::new(DEFAULT_CONFIG_FILE).is_file() => {
+                Some(fs::read_to_string(DEFAULT_CONFIG_FILE).map_err(|err| {
+                    ConfigError::Read(DEFAULT_CONFIG_FILE.to_string(), err.to_string())
+                })?)
+            }
+            None => None,
+        };
+
+        Self::from_sources(contents.as_deref(), std::env::vars())
+    }
+
+    /// Build the config from the contents of a config file, overridden by the given environment
+    /// variables, and validate it. Keys missing from both take their default value.
+    ///
+    /// A variable's value is read as a TOML value, e.g. `8082` or `["https://example.com"]`,
+    /// and as a plain string if it isn't one. Empty variables are ignored.
+    pub fn from_sources(
+        contents: Option<&str>,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let mut table = match contents {
+            Some(contents) => toml::from_str::<Table>(contents)
+                .map_err(|err| ConfigError::Malformed(err.to_string()))?,
+            None => Table::new(),
+        };
+
+        let vars = vars
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .collect::<BTreeMap<_, _>>();
+
+        // The prefixed variables are applied last, so that they win over the aliases.
+        for (name, key) in ENV_ALIASES {
+            if let Some(value) = vars.get(*name) {
+                set_key(&mut table, &key.split('.').collect::<Vec<_>>(), value)?;
+            }
+        }
+        for (name, value) in &vars {
+            if let Some(key) = name.strip_prefix(ENV_PREFIX) {
+                let key = key.to_lowercase();
+                set_key(&mut table, &key.split("__").collect::<Vec<_>>(), value)?;
+            }
+        }
+
+        let config = Value::Table(table)
+            .try_into::<Config>()
+            .map_err(|err| ConfigError::Malformed(err.to_string()))?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Check the values that can't be expressed by their type alone, reporting every problem at once.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.server.bind_address.parse::<IpAddr>().is_err() {
+            problems.push(format!(
+                "server.bind_address `{}` is not an IP address",
+                self.server.bind_address
+            ));
+        }
+
+        if !is_http_url(&self.upstream.url) {
+            problems.push(format!(
+                "upstream.url `{}` is not an HTTP(S) URL",
+                self.upstream.url
+            ));
+        }
+        if let Some(source_file) = &self.upstream.source_file {
+            if !Path::new(source_file).is_file() {
+                problems.push(format!(
+                    "upstream.source_file `{source_file}` doesn't exist"
+                ));
+            }
+        }
+        if self.upstream.connect_timeout_secs == 0 || self.upstream.timeout_secs == 0 {
+            problems.push("upstream timeouts must be greater than 0".to_string());
+        } else if self.upstream.connect_timeout_secs > self.upstream.timeout_secs {
+            problems.push(
+                "upstream.connect_timeout_secs must not be greater than upstream.timeout_secs"
+                    .to_string(),
+            );
+        }
+        if self.upstream.circuit_breaker_threshold == 0 {
+            problems.push("upstream.circuit_breaker_threshold must be greater than 0".to_string());
+        }
+
+        if self.storage.database_path.is_empty() || self.storage.snapshots_dir.is_empty() {
+            problems.push("storage paths must not be empty".to_string());
+        }
+
+        if self.cache.backend == CacheBackend::Redis
+            && self
+                .cache
+                .redis_url
+                .as_str()
+                .into_connection_info()
+                .is_err()
+        {
+            problems.push(format!(
+                "cache.redis_url `{}` is not a Redis URL",
+                self.cache.redis_url
+            ));
+        }
+        if self.cache.ttl_secs == 0 {
+            problems.push("cache.ttl_secs must be greater than 0".to_string());
+        }
+        for (prefix, ttl_secs) in &self.cache.route_ttl_secs {
+            if !prefix.starts_with('/') {
+                problems.push(format!(
+                    "cache.route_ttl_secs path `{prefix}` must start with `/`"
+                ));
+            }
+            if *ttl_secs == 0 {
+                problems.push(format!(
+                    "cache.route_ttl_secs of `{prefix}` must be greater than 0"
+                ));
+            }
+        }
+
+        for origin in &self.cors.allowed_origins {
+            if origin != "*" && !is_origin(origin) {
+                problems.push(format!(
+                    "cors.allowed_origins `{origin}` is neither `*` nor an origin like `https://example.com`"
+                ));
+            }
+        }
+        for method in &self.cors.allowed_methods {
+            if Method::from_bytes(method.as_bytes()).is_err() {
+                problems.push(format!("cors.allowed_methods `{method}` is not a method"));
+            }
+        }
+        for header in self
+            .cors
+            .allowed_headers
+            .iter()
+            .chain(&self.cors.exposed_headers)
+        {
+            if HeaderName::from_bytes(header.as_bytes()).is_err() {
+                problems.push(format!("cors header `{header}` is not a header name"));
+            }
+        }
+
+        if let Some(otlp_endpoint) = &self.logging.otlp_endpoint {
+            if !is_http_url(otlp_endpoint) {
+                problems.push(format!(
+                    "logging.otlp_endpoint `{otlp_endpoint}` is not an HTTP(S) URL"
+                ));
+            }
+        }
+
+        if self.rate_limit.burst == 0 || self.rate_limit.requests_per_minute == 0 {
+            problems.push(
+                "rate_limit.burst and rate_limit.requests_per_minute must be greater than 0"
+                    .to_string(),
+            );
+        }
+        for path in &self.rate_limit.exempt_paths {
+            if !path.starts_with('/') {
+                problems.push(format!(
+                    "rate_limit.exempt_paths path `{path}` must start with `/`"
+                ));
+            }
+        }
+
+        if self.auth.anonymous_scopes.contains(&AuthScope::Admin) {
+            problems.push("auth.anonymous_scopes can't include `admin`".to_string());
+        }
+        for path in &self.auth.exempt_paths {
+            if !path.starts_with('/') {
+                problems.push(format!(
+                    "auth.exempt_paths path `{path}` must start with `/`"
+                ));
+            }
+        }
+        if self.auth.enabled {
+            match self.auth.load_keys() {
+                Ok(keys) => problems.extend(api_keys_problems(&keys)),
+                Err(err) => problems.push(format!("auth.keys_file: {err}")),
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems.join("; ")))
+        }
+    }
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Trace => "trace",
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+            Self::Off => "off",
+        }
+    }
+}
+
+impl UpstreamConfig {
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.refresh_interval_secs)
+    }
+
+    pub fn retry_backoff(&self) -> Duration {
+        Duration::from_millis(self.retry_backoff_ms)
+    }
+
+    pub fn circuit_breaker_cooldown(&self) -> Duration {
+        Duration::from_secs(self.circuit_breaker_cooldown_secs)
+    }
+}
+
+impl RateLimitConfig {
+    pub fn is_exempt(&self, path: &str) -> bool {
+        is_under(path, &self.exempt_paths)
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allow_anonymous: true,
+            anonymous_scopes: vec![
+                AuthScope::Daily,
+                AuthScope::Monthly,
+                AuthScope::Yearly,
+                AuthScope::Exports,
+            ],
+            keys: Vec::new(),
+            keys_file: None,
+            exempt_paths: vec![
+                "/health".to_string(),
+                "/metrics".to_string(),
+                "/docs".to_string(),
+                "/api-doc".to_string(),
+            ],
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn is_exempt(&self, path: &str) -> bool {
+        is_under(path, &self.exempt_paths)
+    }
+
+    /// The keys of the config followed by the ones of `keys_file`.
+    pub fn load_keys(&self) -> Result<Vec<ApiKeyConfig>, ConfigError> {
+        let mut keys = self.keys.clone();
+
+        if let Some(keys_file) = &self.keys_file {
+            let contents = fs::read_to_string(keys_file)
+                .map_err(|err| ConfigError::Read(keys_file.clone(), err.to_string()))?;
+            let file = toml::from_str::<ApiKeysFile>(&contents)
+                .map_err(|err| ConfigError::Malformed(format!("{keys_file}: {err}")))?;
+            keys.extend(file.keys);
+        }
+
+        Ok(keys)
+    }
+}
+
+impl AuthScope {
+    /// Scope of the route group `path` belongs to, if any.
+    pub fn of_path(path: &str) -> Option<Self> {
+        // Every path is under `/`, which only stands for the summary.
+        if path == "/" {
+            return Some(Self::Daily);
+        }
+
+        [
+            ("/daily", Self::Daily),
+            ("/aggregate", Self::Daily),
+            ("/compare", Self::Daily),
+            ("/insights", Self::Daily),
+            ("/quality", Self::Daily),
+            ("/snapshots", Self::Daily),
+            ("/monthly", Self::Monthly),
+            ("/yearly", Self::Yearly),
+            ("/admin", Self::Admin),
+        ]
+        .into_iter()
+        .find(|(prefix, _)| is_under(path, &[prefix.to_string()]))
+        .map(|(_, scope)| scope)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Monthly => "monthly",
+            Self::Yearly => "yearly",
+            Self::Exports => "exports",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl CacheConfig {
+    /// How long the response of a request to `path` is cached, in seconds.
+    pub fn ttl_secs_of(&self, path: &str) -> u64 {
+        self.route_ttl_secs
+            .iter()
+            .filter(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.ttl_secs, |(_, ttl_secs)| *ttl_secs)
+    }
+}
+
+/// Use `config` for the whole app, unless a config is already in use.
+pub fn init(config: Config) -> &'static Config {
+    CONFIG.get_or_init(|| config)
+}
+
+/// The config in use, loaded with [`Config::load`] the first time it's needed
+/// if [`init`] wasn't called beforehand.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(|| Config::load().unwrap_or_else(|err| panic!("{err}")))
+}
+
+/// Set `value` at `key` (its path in the config), creating the missing sections along the way.
+fn set_key(table: &mut Table, key: &[&str], value: &str) -> Result<(), ConfigError> {
+    match key {
+        [] => Ok(()),
+        [name] => {
+            table.insert(name.to_string(), parse_value(value));
+            Ok(())
+        }
+        [section, rest @ ..] => match table
+            .entry(section.to_string())
+            .or_insert_with(|| Value::Table(Table::new()))
+        {
+            Value::Table(section) => set_key(section, rest, value),
+            _ => Err(ConfigError::Malformed(format!(
+                "`{section}` is not a section"
+            ))),
+        },
+    }
+}
+
+fn parse_value(value: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+fn api_keys_problems(keys: &[ApiKeyConfig]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut names = HashSet::new();
+    let mut values = HashSet::new();
+
+    for key in keys {
+        if key.name.is_empty() || key.key.is_empty() {
+            problems.push("auth.keys name and key must not be empty".to_string());
+        }
+        if !names.insert(key.name.as_str()) {
+            problems.push(format!("auth.keys name `{}` is used twice", key.name));
+        }
+        if !values.insert(key.key.as_str()) {
+            problems.push(format!("auth.keys key of `{}` is used twice", key.name));
+        }
+        if key.daily_quota == Some(0) {
+            problems.push(format!(
+                "auth.keys daily_quota of `{}` must be greater than 0",
+                key.name
+            ));
+        }
+    }
+
+    problems
+}
+
+/// Whether `path` is one of `prefixes`, or a path under one of them.
+fn is_under(path: &str, prefixes: &[String]) -> bool {
+    prefixes.iter().any(|prefix| {
+        path.strip_prefix(prefix.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })
+}
+
+fn serialize_redacted<S: Serializer>(_: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(REDACTED)
+}
+
+/// Serialize the URL `value` with its password redacted, if it has one.
+fn serialize_redacted_url<S: Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    match Url::parse(value) {
+        Ok(mut url) if url.password().is_some() => {
+            // Only fails for URLs that can't have a password, which this one has.
+            let _ = url.set_password(Some(REDACTED));
+            serializer.serialize_str(url.as_str())
+        }
+        _ => serializer.serialize_str(value),
+    }
+}
+
+fn is_http_url(value: &str) -> bool {
+    Url::parse(value).is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host())
+}
+
+/// Whether `value` is a scheme, a host, and an optional port, without any path.
+fn is_origin(value: &str) -> bool {
+    is_http_url(value)
+        && Url::parse(value).is_ok_and(|url| url.origin().ascii_serialization() == value)
+}
@@ -0,0 +1,230 @@
+use chrono::{naive::MIN_DATE, Datelike, Duration, NaiveDate};
+use chrono_utilities::naive::DateTransitions;
+
+/// Longest span a relative date or a period may cover, far beyond any data.
+const MAX_YEARS: u32 = 1000;
+const MAX_DAYS: u32 = MAX_YEARS * 366;
+
+/// A boundary of a date range, either an absolute date or a date relative to the latest date in the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateBound {
+    Absolute(NaiveDate),
+    /// `today-N`, where "today" is the latest date in the data rather than the wall clock.
+    DaysBeforeLatest(u32),
+}
+
+impl DateBound {
+    /// Parse `today` or `today-N`.<br>
+    /// Returns `None` if the value is not a relative date at all, e.g. an absolute date.
+    pub fn parse_relative(value: &str) -> Option<Result<Self, String>> {
+        let offset = value.strip_prefix("today")?;
+
+        if offset.is_empty() {
+            return Some(Ok(Self::DaysBeforeLatest(0)));
+        }
+
+        let parsed = offset
+            .strip_prefix('-')
+            .and_then(|days| days.parse::<u32>().ok())
+            .filter(|days| *days <= MAX_DAYS)
+            .map(Self::DaysBeforeLatest)
+            .ok_or(format!(
+                "Invalid relative date `{value}`, expected `today-N` with N up to {MAX_DAYS}"
+            ));
+
+        Some(parsed)
+    }
+
+    /// Parse either a relative date (`today-N`) or a date in ISO 8601 format (YYYY-MM-DD).
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if let Some(relative) = Self::parse_relative(value) {
+            return relative;
+        }
+
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(Self::Absolute)
+            .map_err(|_| format!("Invalid date `{value}`, expected YYYY-MM-DD or `today-N`"))
+    }
+
+    /// The date of the bound, a relative one earlier than any date being clamped to the earliest.
+    pub fn resolve(&self, latest: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Absolute(date) => *date,
+            Self::DaysBeforeLatest(days) => latest
+                .checked_sub_signed(Duration::days(*days as i64))
+                .unwrap_or(MIN_DATE),
+        }
+    }
+}
+
+/// A period of time that ends at the latest date in the data, e.g. `30d`, `12w`, `6m`, or `1y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+    Years(u32),
+}
+
+impl Period {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Invalid period `{value}`, expected e.g. `30d`, `12w`, `6m`, or `1y`, up to {MAX_YEARS} years"
+            )
+        };
+
+        let unit_position = value.len().checked_sub(1).ok_or_else(invalid)?;
+        let (amount, unit) = value.split_at(unit_position);
+        let amount = amount
+            .parse::<u32>()
+            .ok()
+            .filter(|amount| *amount > 0)
+            .ok_or_else(invalid)?;
+
+        let period = match unit {
+            "d" if amount <= MAX_DAYS => Self::Days(amount),
+            "w" if amount <= MAX_DAYS / 7 => Self::Weeks(amount),
+            "m" if amount <= MAX_YEARS * 12 => Self::Months(amount),
+            "y" if amount <= MAX_YEARS => Self::Years(amount),
+            _ => return Err(invalid()),
+        };
+
+        Ok(period)
+    }
+
+    /// First day of the period that ends at `latest`, clamped to the earliest date.
+    pub fn start(&self, latest: NaiveDate) -> NaiveDate {
+        let start = match self {
+            Self::Days(days) => latest.checked_sub_signed(Duration::days(*days as i64 - 1)),
+            Self::Weeks(weeks) => latest.checked_sub_signed(Duration::days(*weeks as i64 * 7 - 1)),
+            Self::Months(_) | Self::Years(_) => self
+                .months()
+                .and_then(|months| checked_shift_months(latest, -months))
+                .and_then(|start| start.succ_opt()),
+        };
+
+        start.unwrap_or(MIN_DATE)
+    }
+
+    /// Length of the period in months, `None` if it's measured in days or weeks, or too long.
+    fn months(&self) -> Option<i32> {
+        let months = match self {
+            Self::Months(months) => *months,
+            Self::Years(years) => years.checked_mul(12)?,
+            Self::Days(_) | Self::Weeks(_) => return None,
+        };
+
+        i32::try_from(months).ok()
+    }
+}
+
+/// The period a range is compared against.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Baseline {
+    /// A range of the same length that ends right before the compared range.
+    Previous,
+    /// The same range, a year before.
+    YearAgo,
+}
+
+impl Baseline {
+    /// The baseline of the range from `since` to `upto`, `None` when it's earlier than any date.
+    pub fn of(&self, since: NaiveDate, upto: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        match self {
+            Self::Previous => {
+                let baseline_upto = since.pred_opt()?;
+                Some((
+                    baseline_upto.checked_sub_signed(upto - since)?,
+                    baseline_upto,
+                ))
+            }
+            Self::YearAgo => Some((
+                checked_shift_months(since, -12)?,
+                checked_shift_months(upto, -12)?,
+            )),
+        }
+    }
+}
+
+/// Date range requested through the `since`, `upto`, and `last` query params.
+#[derive(Debug, Clone, Default)]
+pub struct DateRange {
+    pub since: Option<DateBound>,
+    pub upto: Option<DateBound>,
+    pub last: Option<Period>,
+}
+
+impl DateRange {
+    /// Resolve the range into absolute, inclusive boundaries.
+    ///
+    /// Relative boundaries are resolved against `latest`, the latest date in the data,
+    /// they're dropped if there's no data at all. When both `since` and `last` are given,
+    /// the later of the two wins.
+    pub fn resolve(&self, latest: Option<NaiveDate>) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        let resolve_bound = |bound: &DateBound| match (bound, latest) {
+            (DateBound::Absolute(date), _) => Some(*date),
+            (relative, Some(latest)) => Some(relative.resolve(latest)),
+            (_, None) => None,
+        };
+
+        let since = self.since.as_ref().and_then(resolve_bound);
+        let since_last = self
+            .last
+            .zip(latest)
+            .map(|(last, latest)| last.start(latest));
+        let since = since.max(since_last);
+
+        (since, self.upto.as_ref().and_then(resolve_bound))
+    }
+
+    /// Same as [`DateRange::resolve`], but widened to whole months.
+    ///
+    /// A `last` period measured in months or years counts the latest month as the first one,
+    /// e.g. `6m` is the latest month and the 5 months before it.
+    pub fn resolve_months(
+        &self,
+        latest: Option<NaiveDate>,
+    ) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        let latest_month = latest.and_then(|latest| latest.start_of_month());
+        let range = match self.last {
+            Some(Period::Months(_) | Period::Years(_)) => DateRange {
+                since: self.since,
+                upto: self.upto,
+                last: None,
+            },
+            _ => self.clone(),
+        };
+
+        let (since, upto) = range.resolve(latest);
+        let since_last = match (self.last, latest_month) {
+            (Some(last @ (Period::Months(_) | Period::Years(_))), Some(latest_month)) => Some(
+                last.months()
+                    .and_then(|months| checked_shift_months(latest_month, 1 - months))
+                    .unwrap_or(MIN_DATE),
+            ),
+            _ => None,
+        };
+
+        (
+            since
+                .max(since_last)
+                .and_then(|since| since.start_of_month()),
+            upto.and_then(|upto| upto.end_of_month()),
+        )
+    }
+}
+
+/// Move `date` by the given number of months, clamping the day to the length of the target month.<br>
+/// Returns `None` when the target month is out of the supported range of dates.
+pub(crate) fn checked_shift_months(date: NaiveDate, months: i32) -> Option<NaiveDate> {
+    let months_since_epoch = (date.year() * 12 + date.month0() as i32).checked_add(months)?;
+    let (year, month) = (
+        months_since_epoch.div_euclid(12),
+        months_since_epoch.rem_euclid(12) as u32 + 1,
+    );
+    let last_day = NaiveDate::from_ymd_opt(year, month, 1)?.last_day_of_month();
+
+    NaiveDate::from_ymd_opt(year, month, date.day().min(last_day))
+}
@@ -0,0 +1,84 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// Metrics of a daily, monthly, or yearly case that can be selected with the `fields` query param.
+pub const METRICS: [&str; 4] = ["positive", "recovered", "deaths", "active"];
+
+/// Metrics to be included in a response, the date keys (`year`, `month`, `day`) are always included.
+#[derive(Debug, Clone, Default)]
+pub struct Fields {
+    /// `None` means every metric is selected.
+    selected: Option<Vec<&'static str>>,
+}
+
+impl Fields {
+    /// Parse a comma separated list of metrics, e.g. `positive,deaths`.
+    pub fn parse(fields: &str) -> Result<Self, String> {
+        let mut selected = Vec::new();
+
+        for field in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let metric = METRICS
+                .iter()
+                .find(|metric| **metric == field)
+                .ok_or(format!(
+                    "Unknown field `{field}`, valid fields are {}",
+                    METRICS.join(", ")
+                ))?;
+
+            if !selected.contains(metric) {
+                selected.push(*metric);
+            }
+        }
+
+        if selected.is_empty() {
+            return Err("fields must contain at least one field".into());
+        }
+
+        Ok(Self {
+            selected: Some(selected),
+        })
+    }
+
+    pub fn from_query_param(fields: Option<&str>) -> Result<Self, String> {
+        fields
+            .map(Self::parse)
+            .unwrap_or_else(|| Ok(Self::default()))
+    }
+
+    /// Serialize `data` with the unselected metrics removed from every object in it.
+    pub fn select<T: Serialize>(&self, data: &T) -> Result<Value, String> {
+        let mut value = serde_json::to_value(data).map_err(|err| err.to_string())?;
+
+        if let Some(selected) = &self.selected {
+            match &mut value {
+                Value::Array(items) => items
+                    .iter_mut()
+                    .for_each(|item| retain_metrics(item, selected)),
+                item => retain_metrics(item, selected),
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+fn retain_metrics(item: &mut Value, selected: &[&str]) {
+    if let Value::Object(object) = item {
+        object
+            .retain(|key, _| !METRICS.contains(&key.as_str()) || selected.contains(&key.as_str()));
+    }
+}
+
+/// Canonical form of a `fields` query param value, so that `deaths,positive` and `positive,deaths`
+/// share the same cache entry.
+pub fn canonicalize(fields: &str) -> String {
+    let mut fields = fields
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .collect::<Vec<_>>();
+
+    fields.sort_unstable();
+    fields.dedup();
+    fields.join(",")
+}
@@ -0,0 +1,87 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::Deserialize;
+
+use crate::types::{DailyCase, DailyCases, FilledDailyCase};
+
+/// How the days missing from the daily series are filled, taken from the `fill` query param.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Fill {
+    /// Every metric is 0.
+    Zero,
+    /// Every metric is `null`.
+    Null,
+    /// Every metric is linearly interpolated between the surrounding days, rounded to a whole number.
+    Interpolate,
+}
+
+impl DailyCases {
+    /// First and last day of every calendar gap between the daily cases, ordered by date.
+    pub fn gaps(&self) -> Vec<(NaiveDate, NaiveDate)> {
+        self.0
+            .iter()
+            .zip(self.0.iter().skip(1))
+            .filter(|(previous, next)| next.date() - previous.date() > Duration::days(1))
+            .map(|(previous, next)| {
+                (
+                    previous.date() + Duration::days(1),
+                    next.date() - Duration::days(1),
+                )
+            })
+            .collect()
+    }
+
+    /// Turn the daily cases into a contiguous series, with the missing days filled in and marked as such.
+    pub fn fill(self, fill: Fill) -> Vec<FilledDailyCase> {
+        let mut series = Vec::with_capacity(self.0.len());
+
+        for (index, daily) in self.0.iter().enumerate() {
+            if let Some(previous) = index.checked_sub(1).map(|index| &self.0[index]) {
+                let days_between = (daily.date() - previous.date()).num_days();
+
+                for offset in 1..days_between {
+                    let date = previous.date() + Duration::days(offset);
+                    let metric = |previous: i32, next: i32| match fill {
+                        Fill::Zero => Some(0),
+                        Fill::Null => None,
+                        Fill::Interpolate => Some(
+                            (previous as f64
+                                + (next - previous) as f64 * offset as f64 / days_between as f64)
+                                .round() as i32,
+                        ),
+                    };
+
+                    series.push(FilledDailyCase {
+                        year: date.year(),
+                        month: date.month(),
+                        day: date.day(),
+                        positive: metric(previous.positive, daily.positive),
+                        recovered: metric(previous.recovered, daily.recovered),
+                        deaths: metric(previous.deaths, daily.deaths),
+                        active: metric(previous.active, daily.active),
+                        filled: true,
+                    });
+                }
+            }
+
+            series.push(FilledDailyCase::from(daily));
+        }
+
+        series
+    }
+}
+
+impl From<&DailyCase> for FilledDailyCase {
+    fn from(daily: &DailyCase) -> Self {
+        Self {
+            year: daily.year,
+            month: daily.month,
+            day: daily.day,
+            positive: Some(daily.positive),
+            recovered: Some(daily.recovered),
+            deaths: Some(daily.deaths),
+            active: Some(daily.active),
+            filled: false,
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use chrono_utilities::naive::DateTransitions;
+use serde::Deserialize;
+
+use crate::types::{CaseTotals, DailyCases, MetricRecords, Record, Records, Wave};
+
+/// Metric that waves are detected on.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    #[default]
+    Positive,
+    Deaths,
+    Active,
+}
+
+impl Metric {
+    fn of(&self, totals: &CaseTotals) -> i32 {
+        match self {
+            Self::Positive => totals.positive,
+            Self::Deaths => totals.deaths,
+            Self::Active => totals.active,
+        }
+    }
+}
+
+/// Options of the wave detection.
+#[derive(Debug, Clone, Copy)]
+pub struct WaveOptions {
+    pub metric: Metric,
+    /// Minimum prominence of a peak, relative to the highest smoothed value (between 0 and 1).
+    pub prominence: f64,
+    /// Size of the centered moving average the series is smoothed with, in days.
+    pub window: usize,
+}
+
+impl Default for WaveOptions {
+    fn default() -> Self {
+        Self {
+            metric: Metric::default(),
+            prominence: 0.1,
+            window: 7,
+        }
+    }
+}
+
+type Bucket = ((NaiveDate, NaiveDate), CaseTotals);
+
+impl DailyCases {
+    /// Highest daily, weekly, and monthly values of `positive`, `deaths`, and `active`.<br>
+    /// Returns `None` if there are no daily cases at all.
+    pub fn records(&self) -> Option<MetricRecords> {
+        let daily = self.fold_by(|daily| (daily.date(), daily.date()));
+        let weekly = self.fold_by(|daily| {
+            let date = daily.date();
+            let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+            (monday, monday + Duration::days(6))
+        });
+        let monthly = self.fold_by(|daily| {
+            let first_day = NaiveDate::from_ymd(daily.year, daily.month, 1);
+            (
+                first_day,
+                first_day.with_day(first_day.last_day_of_month()).unwrap(),
+            )
+        });
+
+        let records_of = |metric: Metric| {
+            Some(Records {
+                daily: highest(&daily, metric)?,
+                weekly: highest(&weekly, metric)?,
+                monthly: highest(&monthly, metric)?,
+            })
+        };
+
+        Some(MetricRecords {
+            positive: records_of(Metric::Positive)?,
+            deaths: records_of(Metric::Deaths)?,
+            active: records_of(Metric::Active)?,
+        })
+    }
+
+    /// Detect the waves of a metric, i.e. the peaks of its smoothed series
+    /// that are at least as prominent as requested.
+    ///
+    /// The prominence of a peak is its height above the higher of the two lowest points
+    /// between it and the nearest higher values on either side. A wave spans from the lowest
+    /// point before its peak to the lowest point after it, bounded by the neighbouring waves.
+    pub fn waves(&self, options: WaveOptions) -> Vec<Wave> {
+        let daily = self.fold_by(|daily| daily.date());
+        let dates = daily.iter().map(|(date, _)| *date).collect::<Vec<_>>();
+        let values = smooth(
+            &daily
+                .iter()
+                .map(|(_, totals)| options.metric.of(totals) as f64)
+                .collect::<Vec<_>>(),
+            options.window,
+        );
+
+        let highest = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let min_prominence = highest.abs() * options.prominence;
+
+        let peaks = (0..values.len())
+            .filter(|&i| {
+                (i == 0 || values[i - 1] < values[i])
+                    && (i + 1 == values.len() || values[i + 1] <= values[i])
+            })
+            .map(|i| (i, prominence(&values, i)))
+            .filter(|(_, prominence)| *prominence > 0.0 && *prominence >= min_prominence)
+            .collect::<Vec<_>>();
+
+        peaks
+            .iter()
+            .enumerate()
+            .map(|(n, &(peak, prominence))| {
+                let start = n.checked_sub(1).map_or(0, |previous| peaks[previous].0);
+                let end = peaks.get(n + 1).map_or(values.len() - 1, |next| next.0);
+
+                Wave {
+                    start: dates[lowest_between(&values, start, peak)].to_string(),
+                    peak: dates[peak].to_string(),
+                    end: dates[lowest_between(&values, peak, end)].to_string(),
+                    peak_value: round(values[peak]),
+                    prominence: round(prominence),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Bucket with the highest value of the metric, the earliest one wins a tie.
+fn highest(buckets: &[Bucket], metric: Metric) -> Option<Record> {
+    buckets
+        .iter()
+        .rev()
+        .max_by_key(|(_, totals)| metric.of(totals))
+        .map(|((since, upto), totals)| Record {
+            since: since.to_string(),
+            upto: upto.to_string(),
+            value: metric.of(totals),
+        })
+}
+
+/// Centered moving average, the window shrinks near both ends of the series.
+fn smooth(values: &[f64], window: usize) -> Vec<f64> {
+    let (before, after) = ((window - 1) / 2, window / 2);
+
+    (0..values.len())
+        .map(|i| {
+            let neighbours = &values[i.saturating_sub(before)..(i + after + 1).min(values.len())];
+            neighbours.iter().sum::<f64>() / neighbours.len() as f64
+        })
+        .collect()
+}
+
+fn prominence(values: &[f64], peak: usize) -> f64 {
+    let lowest_of = |range: &mut dyn Iterator<Item = usize>| {
+        range
+            .take_while(|&i| values[i] <= values[peak])
+            .map(|i| values[i])
+            .fold(values[peak], f64::min)
+    };
+
+    let left_base = lowest_of(&mut (0..peak).rev());
+    let right_base = lowest_of(&mut (peak + 1..values.len()));
+
+    values[peak] - left_base.max(right_base)
+}
+
+/// Index of the lowest value between `from` and `to` (both inclusive), the earliest one wins a tie.
+fn lowest_between(values: &[f64], from: usize, to: usize) -> usize {
+    (from..=to).fold(from, |lowest, i| {
+        if values[i] < values[lowest] {
+            i
+        } else {
+            lowest
+        }
+    })
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
@@ -1,5 +1,24 @@
+pub mod aggregation;
 pub mod api_doc;
+pub mod auth;
+pub mod cache;
+pub mod cli;
+pub mod compression;
+pub mod config;
+pub mod date_range;
+pub mod fields;
+pub mod fill;
+pub mod insights;
 pub mod middleware;
+pub mod pagination;
+pub mod quality;
+pub mod rate_limit;
+pub mod response;
 pub mod routes;
+pub mod single_flight;
+pub mod snapshots;
+pub mod storage;
+pub mod telemetry;
 pub mod types;
+pub mod upstream;
 pub mod utils;
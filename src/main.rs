@@ -1,35 +1,151 @@
-use std::io::ErrorKind;
+use std::{
+    fs,
+    io::{self, ErrorKind, Write},
+    process,
+    sync::Arc,
+};
 
 use actix_web::{web, App, HttpServer};
 use actix_web_lab::middleware::from_fn;
+use clap::Parser;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 use rust_covid_api::{
     api_doc::ApiDoc,
+    auth::ApiKeys,
+    cache::{self, RedisCache},
+    cli::{self, Cli, Command},
+    config::{self, CacheBackend, Config},
     middleware,
+    rate_limit::RateLimiter,
     routes::{self, daily, monthly},
+    storage::SharedStorage,
+    telemetry, utils,
 };
 
 #[actix_web::main]
-async fn main() -> Result<(), impl std::error::Error> {
-    let port = std::env::var("PORT")
-        .ok()
-        .map(|val| val.parse::<u16>())
-        .unwrap_or(Ok(8082))
-        .map_err(|_| std::io::Error::new(ErrorKind::InvalidInput, "Invalid port configuration."))?;
+async fn main() {
+    let cli = Cli::parse();
+
+    let result = match cli.command.unwrap_or(Command::Serve) {
+        Command::CheckConfig => cli::check_config(cli.config.as_deref()).and_then(print),
+        Command::Openapi => cli::openapi().and_then(print),
+        command => match Config::load_from(cli.config.as_deref()) {
+            Ok(config) => {
+                let config = config::init(config);
+                match telemetry::init(&config.logging) {
+                    Ok(()) => run(command, config).await,
+                    Err(err) => Err(err),
+                }
+            }
+            Err(err) => Err(err.to_string()),
+        },
+    };
+    telemetry::shutdown();
+
+    if let Err(err) = result {
+        eprintln!("Error: {err}");
+        process::exit(1);
+    }
+}
+
+async fn run(command: Command, config: &'static Config) -> Result<(), String> {
+    match command {
+        Command::Fetch(args) => {
+            let (body, description) = cli::fetch().await?;
+            fs::write(&args.output, body).map_err(|err| err.to_string())?;
+            eprintln!("Wrote {description} to {}", args.output);
+            Ok(())
+        }
+        Command::Export(args) => {
+            let data = cli::export(&args).await?;
+            match &args.output {
+                Some(output) => fs::write(output, data).map_err(|err| err.to_string()),
+                None => print(data),
+            }
+        }
+        _ => serve(config).await.map_err(|err| err.to_string()),
+    }
+}
+
+/// Write to the standard output, without panicking when it's closed early, e.g. by `head`.
+fn print(output: impl AsRef<[u8]>) -> Result<(), String> {
+    match io::stdout().write_all(output.as_ref()) {
+        Err(err) if err.kind() != ErrorKind::BrokenPipe => Err(err.to_string()),
+        _ => Ok(()),
+    }
+}
+
+async fn serve(config: &'static Config) -> io::Result<()> {
+    // Opened and migrated once, then shared by the workers.
+    let storage = SharedStorage::open_from_config()
+        .map(web::Data::new)
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+    // Booting from a local dataset fills the storage up front, so that it's served without ever
+    // reaching the source API.
+    if config.upstream.source_file.is_some() {
+        utils::ingest_into_storage(&storage)
+            .await
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+    }
 
     let openapi = ApiDoc::openapi();
 
-    let redis_client =
-        redis::Client::open("redis://127.0.0.1/").expect("Failed connecting to Redis.");
+    let redis_cache = match config.cache.backend {
+        CacheBackend::Redis => Some(web::Data::new(RedisCache::new(
+            redis::Client::open(config.cache.redis_url.as_str())
+                .expect("Failed connecting to Redis."),
+        ))),
+        CacheBackend::None => None,
+    };
+
+    // Shared by the workers, so that a client's requests count against a single bucket.
+    let rate_limiter = if config.rate_limit.enabled {
+        let limiter = RateLimiter::new(config.rate_limit.clone(), &config.cache.redis_url)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidInput, err))?;
+        Some(Arc::new(limiter))
+    } else {
+        None
+    };
+
+    let api_keys = if config.auth.enabled {
+        let api_keys = ApiKeys::from_config(&config.auth)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidInput, err))?;
+        Some(Arc::new(api_keys))
+    } else {
+        None
+    };
+
+    // Moved into the workers' factory, so kept for the cache warm-up to ingest the data into.
+    let warm_up_storage = storage.get_ref().clone();
+
+    let server = HttpServer::new(move || {
+        let app = App::new().app_data(storage.clone());
+        let app = match &redis_cache {
+            Some(redis_cache) => app.app_data(redis_cache.clone()),
+            None => app,
+        };
+        let app = match &api_keys {
+            Some(api_keys) => app.app_data(web::Data::from(api_keys.clone())),
+            None => app,
+        };
 
-    HttpServer::new(move || {
-        App::new()
-            .app_data(redis_client.clone())
+        app.wrap(middleware::Compress)
             .wrap(middleware::CacheResponse)
+            // Outside of the cache, so that cached responses are authenticated and limited too.
+            .wrap(middleware::Authenticate::new(api_keys.clone()))
+            .wrap(middleware::RateLimit::new(rate_limiter.clone()))
+            // Outside of the authentication and the limits, which don't apply to preflight requests.
+            .wrap(middleware::Cors::new(config.cors.clone()))
+            // Registered last to be the outermost, so that they cover the other middlewares.
+            .wrap(middleware::RequestLog)
+            .wrap(middleware::AssignRequestId)
             .route("/", web::get().to(routes::index::daily_cases_summary))
             .route("/health", web::get().to(routes::health::service_health))
+            .route("/quality", web::get().to(routes::quality::quality_report))
+            .route("/admin/usage", web::get().to(routes::admin::key_usage))
             .service(
                 web::scope("/yearly")
                     .service(routes::yearly::all_years)
@@ -46,13 +162,28 @@ async fn main() -> Result<(), impl std::error::Error> {
                 web::scope("/daily")
                     .wrap(from_fn(daily::middleware::filter_malformed_query_params))
                     .service(routes::daily::all_days)
+                    // Must be registered before `/{year}`, otherwise "latest" is matched as a year.
+                    .service(routes::daily::latest_day)
                     .service(routes::daily::all_days_in_a_year)
                     .service(routes::daily::all_days_in_a_month)
                     .service(routes::daily::specific_day),
             )
+            .service(web::scope("/aggregate").service(routes::aggregate::aggregate))
+            .service(web::scope("/compare").service(routes::compare::compare))
+            .service(web::scope("/insights").service(routes::insights::peaks))
+            .service(
+                web::scope("/snapshots")
+                    .service(routes::snapshots::all_snapshots)
+                    .service(routes::snapshots::diff_snapshots),
+            )
             .service(SwaggerUi::new("/docs/{_:.*}").url("/api-doc/openapi.json", openapi.clone()))
     })
-    .bind(("0.0.0.0", port))?
-    .run()
-    .await
+    .bind((config.server.bind_address.as_str(), config.server.port))?;
+
+    // Once bound, so that the warm-up's requests are answered as soon as the server runs.
+    if config.cache.backend == CacheBackend::Redis && config.cache.warm_up {
+        cache::enable_warm_up(&config.server, warm_up_storage);
+    }
+
+    server.run().await
 }
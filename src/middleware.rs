@@ -1,15 +1,45 @@
-use std::future::{ready, Ready};
+use std::{
+    future::{ready, Future, Ready},
+    net::SocketAddr,
+    rc::Rc,
+    sync::Arc,
+    time::Instant,
+};
 
 use actix_web::{
     body::BoxBody,
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    http::header::ContentType,
-    web::Bytes,
-    Error, HttpResponseBuilder,
+    error::InternalError,
+    http::{
+        header::{
+            HeaderMap, HeaderName, HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS,
+            ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+            ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS,
+            ACCESS_CONTROL_REQUEST_METHOD, AGE, CONTENT_ENCODING, CONTENT_TYPE, ORIGIN,
+            RETRY_AFTER, VARY,
+        },
+        Method,
+    },
+    web, Error, HttpMessage, HttpResponse, HttpResponseBuilder,
 };
+use chrono::Utc;
 use futures_util::future::LocalBoxFuture;
-use redis::Commands;
+use redis::{aio::ConnectionManager, AsyncCommands};
 use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+use uuid::Uuid;
+
+use crate::{
+    auth::ApiKeys,
+    cache::{self, RedisCache},
+    compression::ContentEncoding,
+    config::{self, AuthScope, CacheBackend, CorsConfig, RateLimitConfig, RateLimitKey},
+    fields,
+    rate_limit::{RateLimitDecision, RateLimiter, API_KEY_HEADER},
+    response::ResponseFormat,
+    single_flight::{Flight, SingleFlight},
+};
 
 /// This is the middleware factory, use this instead of `CacheResponseMiddleware`.
 pub struct CacheResponse;
@@ -17,75 +47,1007 @@ pub struct CacheResponse;
 impl<S> Transform<S, ServiceRequest> for CacheResponse
 where
     S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S: 'static,
     S::Future: 'static,
 {
-    type Response = ServiceResponse<Bytes>;
+    type Response = ServiceResponse<BoxBody>;
     type Error = Error;
     type InitError = ();
     type Transform = CacheResponseMiddleware<S>;
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(CacheResponseMiddleware { service }))
+        ready(Ok(CacheResponseMiddleware {
+            service: Rc::new(service),
+        }))
     }
 }
 
 pub struct CacheResponseMiddleware<S> {
-    service: S,
+    service: Rc<S>,
 }
 
 impl<S> Service<ServiceRequest> for CacheResponseMiddleware<S>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S: 'static,
     S::Future: 'static,
 {
-    type Response = ServiceResponse<Bytes>;
+    type Response = ServiceResponse<BoxBody>;
     type Error = Error;
     type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
+        // Responses in a format we can't produce aren't worth caching, let the handler reject them.
+        let format = match ResponseFormat::negotiate(req.headers()) {
+            Some(format) => format,
+            None => return Box::pin(self.service.call(req)),
+        };
+
+        let cache_config = &config::get().cache;
+        // The usage of the API keys changes with every request.
+        if cache_config.backend == CacheBackend::None
+            || AuthScope::of_path(req.path()) == Some(AuthScope::Admin)
+        {
+            return Box::pin(self.service.call(req));
+        }
+
         let req_path = req.path().to_owned();
-        let req_queries = format!("?{}", req.query_string().to_owned());
-        let redis_key = format!("{req_path}{req_queries}");
+        let ttl_secs = cache_config.ttl_secs_of(&req_path);
+        // Responses are kept for as long as they may be served stale.
+        let expiry_secs = ttl_secs + cache_config.stale_ttl_secs;
+        let req_queries = format!("?{}", normalize_query_string(req.query_string()));
+        let encoding = ContentEncoding::negotiate(req.headers());
+        let redis_key = format!(
+            "{req_path}{req_queries}#{}#{}",
+            format.as_str(),
+            encoding.as_str()
+        );
+
+        let redis_cache = req.app_data::<web::Data<RedisCache>>().unwrap().clone();
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            // An unavailable cache mustn't fail the request, it's served uncached instead.
+            let mut redis_conn = match redis_cache.connection().await {
+                Ok(redis_conn) => redis_conn,
+                Err(err) => {
+                    tracing::error!(error = %err, "Failed connecting to Redis");
+                    return service.call(req).await;
+                }
+            };
+
+            // Refreshes replace the cached response, rather than being served it.
+            let cached_response = if cache::is_refresh(req.headers()) {
+                None
+            } else {
+                redis_conn
+                    .get::<_, Option<Vec<u8>>>(redis_key.clone())
+                    .instrument(tracing::info_span!("redis_get", key = %redis_key))
+                    .await
+                    .map_err(|err| tracing::warn!(error = %err, "Failed reading from Redis"))
+                    .ok()
+                    .flatten()
+                    .and_then(|cached| rmp_serde::from_slice::<CachedResponse>(&cached).ok())
+            };
+
+            // Responses cached before the TTLs were shortened may outlive them, they're missed.
+            let cached_response = cached_response
+                .map(|cached| (cached.age_secs(), cached))
+                .filter(|(age_secs, _)| *age_secs < expiry_secs);
+
+            if let Some((age_secs, cached_response)) = cached_response {
+                let status = if age_secs < ttl_secs {
+                    CacheStatus::Hit
+                } else {
+                    refresh_in_background(
+                        redis_key,
+                        format!("{req_path}{req_queries}"),
+                        format,
+                        encoding,
+                    );
+                    CacheStatus::Stale
+                };
+
+                req.extensions_mut().insert(status);
+                let (http_req, _) = req.into_parts();
+                let mut response = cached_response.into_response();
+                let headers = response.headers_mut();
+                headers.insert(AGE, HeaderValue::from(age_secs));
+                // The remaining TTL of a stale response is negative.
+                set_cache_status(
+                    headers,
+                    &format!("hit; ttl={}", ttl_secs as i64 - age_secs as i64),
+                );
+
+                return Ok(ServiceResponse::new(http_req, response));
+            }
+
+            req.extensions_mut().insert(CacheStatus::Miss);
+
+            // Concurrent misses of the same response wait for the first one to be cached.
+            let receiver = match CACHE_MISSES.join(redis_key.clone()) {
+                Flight::Leader(leader) => {
+                    let fut = service.call(req);
+                    let (res, cached_response) =
+                        respond_and_cache(fut, redis_conn, redis_key, expiry_secs).await?;
+                    leader.complete(cached_response);
+                    return Ok(res);
+                }
+                Flight::Follower(receiver) => receiver,
+            };
+
+            match receiver.await {
+                Ok(Some(cached_response)) => {
+                    req.extensions_mut().insert(CacheStatus::Collapsed);
+                    let (http_req, _) = req.into_parts();
+                    let mut response = cached_response.into_response();
+                    set_cache_status(response.headers_mut(), "fwd=miss; collapsed");
+
+                    Ok(ServiceResponse::new(http_req, response))
+                }
+                // The first response wasn't cacheable, or its request was cancelled.
+                _ => {
+                    let fut = service.call(req);
+                    let (res, _) =
+                        respond_and_cache(fut, redis_conn, redis_key, expiry_secs).await?;
+                    Ok(res)
+                }
+            }
+        })
+    }
+}
+
+/// Responses being refreshed after being served stale, by their Redis key.
+static CACHE_REFRESHES: SingleFlight<String, ()> = SingleFlight::new();
+
+/// Refresh the cached response of `path_and_query`, unless it's already being refreshed.
+fn refresh_in_background(
+    redis_key: String,
+    path_and_query: String,
+    format: ResponseFormat,
+    encoding: ContentEncoding,
+) {
+    if let Flight::Leader(leader) = CACHE_REFRESHES.join(redis_key) {
+        actix_web::rt::spawn(async move {
+            let server = &config::get().server;
+            if let Err(err) = cache::refresh(server, &path_and_query, format, encoding).await {
+                tracing::warn!(error = %err, "Failed refreshing a stale response");
+            }
+            leader.complete(());
+        });
+    }
+}
+
+/// Respond with the response of `fut`, and cache it for `ttl_secs` if it's successful.
+///
+/// The response is already compressed by [`Compress`], so that cache hits skip both
+/// serialization and compression.
+async fn respond_and_cache(
+    fut: impl Future<Output = Result<ServiceResponse<BoxBody>, Error>>,
+    mut redis_conn: ConnectionManager,
+    redis_key: String,
+    ttl_secs: u64,
+) -> Result<(ServiceResponse<BoxBody>, Option<CachedResponse>), Error> {
+    let res = fut.await?;
+    let (req, res) = res.into_parts();
+    let (mut res, body) = res.into_parts();
+    let body_bytes = actix_web::body::to_bytes(body)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .to_vec();
+    let mut cached_response = None;
+
+    if res.status().is_success() {
+        let response = CachedResponse::new(res.headers(), body_bytes.clone());
+        if let Ok(serialized) = rmp_serde::to_vec(&response) {
+            let stored: redis::RedisResult<()> = redis_conn
+                .set_ex(redis_key.clone(), serialized, ttl_secs as usize)
+                .instrument(tracing::info_span!("redis_set", key = %redis_key, ttl_secs))
+                .await;
+            if let Err(err) = stored {
+                tracing::warn!(error = %err, "Failed writing to Redis");
+            }
+        }
+        cached_response = Some(response);
+    }
+    if cached_response.is_some() {
+        set_cache_status(res.headers_mut(), "fwd=miss; stored");
+    } else {
+        set_cache_status(res.headers_mut(), "fwd=miss");
+    }
+
+    let res = res.set_body(BoxBody::new(body_bytes));
+
+    Ok((ServiceResponse::new(req, res), cached_response))
+}
+
+/// Sort the query params and canonicalize `fields`, so that equivalent requests share a cache entry.
+fn normalize_query_string(query_string: &str) -> String {
+    let mut query_params = match serde_urlencoded::from_str::<Vec<(String, String)>>(query_string) {
+        Ok(query_params) => query_params,
+        Err(_) => return query_string.to_owned(),
+    };
+
+    query_params.iter_mut().for_each(|(key, value)| {
+        if key == "fields" {
+            *value = fields::canonicalize(value);
+        }
+    });
+    query_params.sort();
+
+    serde_urlencoded::to_string(query_params).unwrap_or_else(|_| query_string.to_owned())
+}
+
+/// Responses being computed after a cache miss, by their Redis key.
+static CACHE_MISSES: SingleFlight<String, Option<CachedResponse>> = SingleFlight::new();
 
-        let redis_client = req.app_data::<redis::Client>().unwrap();
-        let mut redis_conn = redis_client.get_connection().unwrap();
+/// A successful response as stored in Redis, its body is already compressed.
+#[derive(Serialize, Deserialize, Clone)]
+struct CachedResponse {
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    /// Unix timestamp of when the response was computed.
+    #[serde(default)]
+    cached_at: i64,
+}
 
-        if let Ok(cached_response) = redis_conn.get::<String, String>(redis_key.clone()) {
-            let (http_req, _) = req.into_parts();
-            let response = HttpResponseBuilder::new(StatusCode::OK)
-                .content_type(ContentType::json())
-                .message_body(Bytes::from(cached_response))
-                .unwrap();
+impl CachedResponse {
+    fn new(headers: &HeaderMap, body: Vec<u8>) -> Self {
+        let headers = headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+            .collect();
 
-            return Box::pin(async { Ok(ServiceResponse::new(http_req, response)) });
+        Self {
+            headers,
+            body,
+            cached_at: Utc::now().timestamp(),
         }
+    }
+
+    fn age_secs(&self) -> u64 {
+        u64::try_from(Utc::now().timestamp() - self.cached_at).unwrap_or(0)
+    }
+
+    fn into_response(self) -> HttpResponse {
+        let mut response = HttpResponseBuilder::new(StatusCode::OK);
+        for header in self.headers {
+            response.append_header(header);
+        }
+
+        response.body(self.body)
+    }
+}
 
+fn set_encoding_headers(headers: &mut HeaderMap, encoding: ContentEncoding) {
+    headers.insert(VARY, HeaderValue::from_static("Accept, Accept-Encoding"));
+
+    if encoding != ContentEncoding::Identity {
+        headers.insert(
+            CONTENT_ENCODING,
+            HeaderValue::from_static(encoding.as_str()),
+        );
+    }
+}
+
+/// Compress the successful responses in the coding negotiated from their `Accept-Encoding`
+/// header.
+///
+/// Must be registered right before [`CacheResponse`] to be inside of it, so that responses are
+/// cached compressed, and compressed all the same when they aren't cached.
+pub struct Compress;
+
+impl<S> Transform<S, ServiceRequest> for Compress
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CompressMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CompressMiddleware { service }))
+    }
+}
+
+pub struct CompressMiddleware<S> {
+    service: S,
+}
+
+impl<S> Service<ServiceRequest> for CompressMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let encoding = ContentEncoding::negotiate(req.headers());
         let fut = self.service.call(req);
 
         Box::pin(async move {
             let res = fut.await?;
+            if !res.status().is_success() {
+                return Ok(res);
+            }
+
             let (req, res) = res.into_parts();
-            let (res, body) = res.into_parts();
-            let body_bytes = actix_web::body::to_bytes(body).await.ok().unwrap();
+            let (mut res, body) = res.into_parts();
+            let body = actix_web::body::to_bytes(body)
+                .await
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            let body = encoding
+                .compress(&body)
+                .map_err(actix_web::error::ErrorInternalServerError)?;
+            set_encoding_headers(res.headers_mut(), encoding);
 
-            if res.status().is_success() {
-                // Cache endpoint response to redis
-                let _: () = redis_conn
-                    .set_ex(
-                        redis_key,
-                        String::from_utf8_lossy(&body_bytes.to_vec()).to_string(),
-                        600,
-                    )
-                    .unwrap();
+            Ok(ServiceResponse::new(req, res.set_body(BoxBody::new(body))))
+        })
+    }
+}
+
+/// Describe how a response went through the cache in its `Cache-Status` header (RFC 9211).
+fn set_cache_status(headers: &mut HeaderMap, status: &str) {
+    let value = format!("{}; {status}", env!("CARGO_PKG_NAME"));
+    if let Ok(value) = HeaderValue::from_str(&value) {
+        headers.insert(HeaderName::from_static("cache-status"), value);
+    }
+}
+
+/// Whether a response was served from the cache, kept in the request's extensions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    Hit,
+    /// The response was served from the cache once no longer fresh, and refreshed in the
+    /// background.
+    Stale,
+    Miss,
+    /// The response was missing from the cache, and shared with a concurrent request that
+    /// missed it first.
+    Collapsed,
+    /// The response isn't cacheable, or caching is disabled.
+    Bypass,
+}
+
+impl CacheStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hit => "hit",
+            Self::Stale => "stale",
+            Self::Miss => "miss",
+            Self::Collapsed => "collapsed",
+            Self::Bypass => "bypass",
+        }
+    }
+}
+
+/// Header a request's id is read from and echoed in.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request id accepted from a client, a longer one is replaced by a generated one.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: RequestId;
+}
+
+/// Id of a request, kept in the request's extensions and included in every log of the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    pub fn generate() -> Self {
+        Self(Uuid::new_v4().to_string())
+    }
+
+    /// Id of the request being handled by the current task, if it's handled behind [`AssignRequestId`].
+    pub fn current() -> Option<Self> {
+        CURRENT_REQUEST_ID.try_with(Self::clone).ok()
+    }
+
+    /// The id sent by the client, if it's made of at most 128 visible ASCII characters.
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get(REQUEST_ID_HEADER)?
+            .to_str()
+            .ok()
+            .filter(|id| (1..=MAX_REQUEST_ID_LEN).contains(&id.len()))
+            .filter(|id| id.bytes().all(|byte| byte.is_ascii_graphic()))
+            .map(|id| Self(id.to_string()))
+    }
+}
+
+/// Give every request an id, the one in its `X-Request-Id` header or a generated one.
+///
+/// The id is echoed in the `X-Request-Id` header of the response, and appended to the body of
+/// error responses so that clients can report it. Must be registered last to be the outermost
+/// middleware.
+pub struct AssignRequestId;
+
+impl<S> Transform<S, ServiceRequest> for AssignRequestId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AssignRequestIdMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AssignRequestIdMiddleware { service }))
+    }
+}
+
+pub struct AssignRequestIdMiddleware<S> {
+    service: S,
+}
+
+impl<S> Service<ServiceRequest> for AssignRequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = RequestId::from_headers(req.headers()).unwrap_or_else(RequestId::generate);
+        req.extensions_mut().insert(request_id.clone());
+
+        let fut = CURRENT_REQUEST_ID.sync_scope(request_id.clone(), || self.service.call(req));
+
+        Box::pin(CURRENT_REQUEST_ID.scope(request_id.clone(), async move {
+            match fut.await {
+                Ok(res) => {
+                    let (http_req, res) = res.into_parts();
+                    let res = with_request_id(res, &request_id).await;
+                    Ok(ServiceResponse::new(http_req, res))
+                }
+                // Errors are responded to further out, their response is replaced by one with the id.
+                Err(err) => {
+                    let res = with_request_id(err.error_response(), &request_id).await;
+                    Err(InternalError::from_response(err.to_string(), res).into())
+                }
+            }
+        }))
+    }
+}
+
+/// Add the `X-Request-Id` header to a response, and the id to its body if it's a plain text error.
+async fn with_request_id(mut res: HttpResponse, request_id: &RequestId) -> HttpResponse {
+    if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+        res.headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+
+    let is_error = res.status().is_client_error() || res.status().is_server_error();
+    let is_plain_text = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_none_or(|content_type| content_type.as_bytes().starts_with(b"text/plain"));
+    if !is_error || !is_plain_text {
+        return res;
+    }
+
+    let (res, body) = res.into_parts();
+    let body = actix_web::body::to_bytes(body).await.unwrap_or_default();
+    let body = if body.is_empty() {
+        format!("Request id: {}", request_id.0)
+    } else {
+        format!(
+            "{} (request id: {})",
+            String::from_utf8_lossy(&body),
+            request_id.0
+        )
+    };
+
+    let mut res = res.set_body(BoxBody::new(body));
+    res.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    res
+}
+
+/// Log every request once it's completed, with its id, route, status, latency, and cache status.
+///
+/// Wraps the handling of the request in an `http_request` span, so it must be registered after
+/// the other middlewares, right before [`AssignRequestId`] whose id it logs.
+pub struct RequestLog;
+
+impl<S> Transform<S, ServiceRequest> for RequestLog
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestLogMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestLogMiddleware { service }))
+    }
+}
+
+pub struct RequestLogMiddleware<S> {
+    service: S,
+}
+
+impl<S> Service<ServiceRequest> for RequestLogMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = req
+            .extensions()
+            .get::<RequestId>()
+            .cloned()
+            .unwrap_or_else(RequestId::generate);
+        let span = tracing::info_span!(
+            "http_request",
+            request_id = %request_id.0,
+            method = %req.method(),
+            route = tracing::field::Empty,
+            path = %req.path(),
+        );
+        if let Some(route) = req.match_pattern() {
+            span.record("route", &route.as_str());
+        }
+
+        let started_at = Instant::now();
+        let fut = span.in_scope(|| self.service.call(req));
+
+        Box::pin(
+            async move {
+                let res = match fut.await {
+                    Ok(res) => res,
+                    Err(err) => {
+                        let status = err.as_response_error().status_code();
+                        let latency_ms = started_at.elapsed().as_secs_f64() * 1000.0;
+                        log_completion(status, latency_ms, CacheStatus::Bypass, Some(&err));
+                        return Err(err);
+                    }
+                };
+                let latency_ms = started_at.elapsed().as_secs_f64() * 1000.0;
+                let cache = res
+                    .request()
+                    .extensions()
+                    .get::<CacheStatus>()
+                    .copied()
+                    .unwrap_or(CacheStatus::Bypass);
+                log_completion(res.status(), latency_ms, cache, res.response().error());
+
+                Ok(res)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+fn log_completion(status: StatusCode, latency_ms: f64, cache: CacheStatus, error: Option<&Error>) {
+    let (status, cache) = (status.as_u16(), cache.as_str());
+
+    match error {
+        Some(err) if status >= 500 => {
+            tracing::error!(status, latency_ms, cache, error = %err, "Request failed")
+        }
+        _ => tracing::info!(status, latency_ms, cache, "Request completed"),
+    }
+}
+
+/// Reject the requests of the clients that ran out of tokens with `429 Too Many Requests`,
+/// and tell every client about its limits through the `RateLimit-*` headers.
+///
+/// Every request is let through when there's no rate limiter, i.e. rate limiting is disabled.
+pub struct RateLimit {
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl RateLimit {
+    pub fn new(limiter: Option<Arc<RateLimiter>>) -> Self {
+        Self { limiter }
+    }
+}
+
+impl<S> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S: 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            limiter: self.limiter.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl<S> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S: 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let limiter = match &self.limiter {
+            Some(limiter)
+                if !limiter.config().is_exempt(req.path()) && !cache::is_refresh(req.headers()) =>
+            {
+                limiter
             }
+            _ => return Box::pin(self.service.call(req)),
+        };
 
-            let res = res.set_body(body_bytes);
-            let res = ServiceResponse::new(req, res);
+        let limiter = limiter.clone();
+        let service = self.service.clone();
 
+        Box::pin(async move {
+            let decision = limiter.check(&client_of(&req, limiter.config())).await;
+
+            if let Some(retry_after_secs) = decision.retry_after_secs {
+                let mut res = HttpResponse::TooManyRequests();
+                res.insert_header((RETRY_AFTER, retry_after_secs));
+                let mut res = res.body(format!(
+                    "Too many requests, retry in {retry_after_secs} second(s)."
+                ));
+                set_rate_limit_headers(res.headers_mut(), &decision);
+
+                return Ok(req.into_response(res));
+            }
+
+            let mut res = service.call(req).await?;
+            set_rate_limit_headers(res.headers_mut(), &decision);
             Ok(res)
         })
     }
 }
+
+/// What the client of a request is told apart by in its rate limits.<br>
+/// The requests are limited before they're authenticated, so only the known API keys tell the
+/// clients apart, the others would let a client make up a new key for every request.
+fn client_of(req: &ServiceRequest, config: &RateLimitConfig) -> String {
+    if config.key == RateLimitKey::ApiKey {
+        let api_keys = req.app_data::<web::Data<ApiKeys>>();
+        if let Some(api_key) = req
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|api_key| api_key.to_str().ok())
+            .filter(|api_key| api_keys.is_some_and(|api_keys| api_keys.contains(api_key)))
+        {
+            return format!("key:{api_key}");
+        }
+    }
+
+    let address = if config.trust_forwarded_for {
+        req.connection_info()
+            .realip_remote_addr()
+            .map(str::to_string)
+    } else {
+        req.peer_addr().map(|address| address.to_string())
+    }
+    .unwrap_or_default();
+
+    // The peer address comes with a port, which changes with every connection.
+    let ip = address
+        .parse::<SocketAddr>()
+        .map(|address| address.ip().to_string())
+        .unwrap_or(address);
+
+    format!("ip:{ip}")
+}
+
+fn set_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    for (name, value) in [
+        ("ratelimit-limit", decision.limit as u64),
+        ("ratelimit-remaining", decision.remaining as u64),
+        ("ratelimit-reset", decision.reset_secs),
+    ] {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+    }
+}
+
+/// Reject the requests without a valid `X-Api-Key`, unless anonymous requests are allowed,
+/// and the ones out of the key's scopes or over its daily quota.
+///
+/// Every request is let through when there are no API keys, i.e. authentication is disabled.
+pub struct Authenticate {
+    api_keys: Option<Arc<ApiKeys>>,
+}
+
+impl Authenticate {
+    pub fn new(api_keys: Option<Arc<ApiKeys>>) -> Self {
+        Self { api_keys }
+    }
+}
+
+impl<S> Transform<S, ServiceRequest> for Authenticate
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthenticateMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthenticateMiddleware {
+            service,
+            api_keys: self.api_keys.clone(),
+        }))
+    }
+}
+
+pub struct AuthenticateMiddleware<S> {
+    service: S,
+    api_keys: Option<Arc<ApiKeys>>,
+}
+
+impl<S> Service<ServiceRequest> for AuthenticateMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let api_keys = match &self.api_keys {
+            Some(api_keys)
+                if !api_keys.config().is_exempt(req.path())
+                    && !cache::is_refresh(req.headers()) =>
+            {
+                api_keys
+            }
+            _ => return Box::pin(self.service.call(req)),
+        };
+
+        let mut scopes = Vec::from_iter(AuthScope::of_path(req.path()));
+        if ResponseFormat::negotiate(req.headers())
+            .is_some_and(|format| format != ResponseFormat::Json)
+        {
+            scopes.push(AuthScope::Exports);
+        }
+
+        let api_key = req
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|api_key| api_key.to_str().ok());
+
+        if let Err(err) = api_keys.authorize(api_key, &scopes) {
+            return Box::pin(async move { Err(err.into()) });
+        }
+
+        Box::pin(self.service.call(req))
+    }
+}
+
+/// Answer the preflight requests of the allowed origins, and let them read the responses,
+/// errors and cached responses included, through the `Access-Control-*` headers.
+///
+/// Every request is let through untouched when no origin is allowed.
+pub struct Cors {
+    config: Arc<CorsConfig>,
+}
+
+impl Cors {
+    pub fn new(config: CorsConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+        }
+    }
+}
+
+impl<S> Transform<S, ServiceRequest> for Cors
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CorsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CorsMiddleware {
+            service,
+            config: self.config.clone(),
+        }))
+    }
+}
+
+pub struct CorsMiddleware<S> {
+    service: S,
+    config: Arc<CorsConfig>,
+}
+
+impl<S> Service<ServiceRequest> for CorsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<BoxBody>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if self.config.allowed_origins.is_empty() {
+            return Box::pin(self.service.call(req));
+        }
+
+        let config = self.config.clone();
+        let origin = req.headers().get(ORIGIN);
+        let allowed_origin = origin.and_then(|origin| allowed_origin_of(&config, origin));
+
+        let is_preflight = req.method() == Method::OPTIONS
+            && origin.is_some()
+            && req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+        if is_preflight {
+            let res = match preflight(&config, req.headers(), allowed_origin.as_ref()) {
+                Ok(res) => res,
+                Err(reason) => HttpResponse::Forbidden().body(reason),
+            };
+
+            return Box::pin(async move { Ok(req.into_response(res)) });
+        }
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            match fut.await {
+                Ok(mut res) => {
+                    set_cors_headers(res.headers_mut(), &config, allowed_origin);
+                    Ok(res)
+                }
+                // Errors are responded to further out, their response is replaced by one the
+                // browser lets the page read.
+                Err(err) => {
+                    let mut res = err.error_response();
+                    set_cors_headers(res.headers_mut(), &config, allowed_origin);
+                    Err(InternalError::from_response(err.to_string(), res).into())
+                }
+            }
+        })
+    }
+}
+
+/// Value of `Access-Control-Allow-Origin` for the requests from `origin`,
+/// `None` when it isn't allowed.
+fn allowed_origin_of(config: &CorsConfig, origin: &HeaderValue) -> Option<HeaderValue> {
+    if config.allowed_origins.iter().any(|allowed| allowed == "*") {
+        return Some(HeaderValue::from_static("*"));
+    }
+
+    config
+        .allowed_origins
+        .iter()
+        .any(|allowed| allowed.as_bytes() == origin.as_bytes())
+        .then(|| origin.clone())
+}
+
+/// Response to a preflight request, or why it's rejected.
+fn preflight(
+    config: &CorsConfig,
+    headers: &HeaderMap,
+    allowed_origin: Option<&HeaderValue>,
+) -> Result<HttpResponse, String> {
+    let allowed_origin = allowed_origin.ok_or("Origin not allowed")?;
+
+    let method = headers
+        .get(ACCESS_CONTROL_REQUEST_METHOD)
+        .and_then(|method| method.to_str().ok())
+        .unwrap_or_default();
+    if !config
+        .allowed_methods
+        .iter()
+        .any(|allowed| allowed == method)
+    {
+        return Err(format!("Method {method} not allowed"));
+    }
+
+    let requested_headers = headers
+        .get(ACCESS_CONTROL_REQUEST_HEADERS)
+        .and_then(|requested| requested.to_str().ok())
+        .unwrap_or_default();
+    for header in requested_headers
+        .split(',')
+        .map(str::trim)
+        .filter(|header| !header.is_empty())
+    {
+        if !config
+            .allowed_headers
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(header))
+        {
+            return Err(format!("Header {header} not allowed"));
+        }
+    }
+
+    let mut res = HttpResponse::NoContent();
+    res.insert_header((ACCESS_CONTROL_ALLOW_ORIGIN, allowed_origin.clone()))
+        .insert_header((
+            ACCESS_CONTROL_ALLOW_METHODS,
+            config.allowed_methods.join(", "),
+        ))
+        .insert_header((ACCESS_CONTROL_MAX_AGE, config.max_age_secs));
+    if !config.allowed_headers.is_empty() {
+        res.insert_header((
+            ACCESS_CONTROL_ALLOW_HEADERS,
+            config.allowed_headers.join(", "),
+        ));
+    }
+    if allowed_origin != "*" {
+        res.insert_header((VARY, "Origin"));
+    }
+
+    Ok(res.finish())
+}
+
+fn set_cors_headers(
+    headers: &mut HeaderMap,
+    config: &CorsConfig,
+    allowed_origin: Option<HeaderValue>,
+) {
+    // Responses differ by origin, unless any is allowed.
+    if !config.allowed_origins.iter().any(|allowed| allowed == "*") {
+        headers.append(VARY, HeaderValue::from_static("Origin"));
+    }
+
+    if let Some(allowed_origin) = allowed_origin {
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allowed_origin);
+        if let Ok(exposed_headers) = HeaderValue::from_str(&config.exposed_headers.join(", ")) {
+            if !exposed_headers.is_empty() {
+                headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, exposed_headers);
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use actix_web::{http::header::LINK, HttpRequest, HttpResponse, HttpResponseBuilder};
+use serde::Deserialize;
+
+use crate::types::QueryParams;
+
+/// Header carrying the number of items before pagination was applied.
+pub const TOTAL_COUNT_HEADER: &str = "X-Total-Count";
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Pagination options of list endpoints, taken from the `limit`, `offset`, and `order` query params.
+#[derive(Debug, Clone, Default)]
+pub struct Pagination {
+    pub limit: Option<usize>,
+    pub offset: usize,
+    pub order: SortOrder,
+}
+
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+}
+
+impl Pagination {
+    pub fn from_query_params(query_params: &QueryParams) -> Result<Self, String> {
+        if query_params.limit == Some(0) {
+            return Err("limit must be greater than 0".into());
+        }
+
+        Ok(Self {
+            limit: query_params.limit,
+            offset: query_params.offset.unwrap_or(0),
+            order: query_params.order.unwrap_or_default(),
+        })
+    }
+
+    /// Sort chronologically ordered `items` by the requested order, then slice the requested page.
+    pub fn paginate<T>(&self, mut items: Vec<T>) -> Page<T> {
+        let total = items.len();
+
+        if self.order == SortOrder::Desc {
+            items.reverse();
+        }
+
+        let items = items
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        Page { items, total }
+    }
+
+    /// Create a `200 OK` response builder carrying the pagination metadata of a page.
+    ///
+    /// `X-Total-Count` is always set, the `Link` header (`first`, `prev`, `next`, `last`)
+    /// is only set when a `limit` is given.
+    pub fn response_builder(&self, req: &HttpRequest, total: usize) -> HttpResponseBuilder {
+        let mut builder = HttpResponse::Ok();
+        builder.insert_header((TOTAL_COUNT_HEADER, total));
+
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return builder,
+        };
+
+        let last_offset = total.saturating_sub(1) / limit * limit;
+        let mut links = vec![(0, "first")];
+
+        if self.offset > 0 {
+            links.push((self.offset.saturating_sub(limit), "prev"));
+        }
+
+        let next_offset = self.offset.saturating_add(limit);
+        if next_offset < total {
+            links.push((next_offset, "next"));
+        }
+
+        links.push((last_offset, "last"));
+
+        let link_header = links
+            .into_iter()
+            .map(|(offset, rel)| format!("<{}>; rel=\"{rel}\"", page_url(req, limit, offset)))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        builder.insert_header((LINK, link_header));
+        builder
+    }
+}
+
+/// The request's URL with its `limit` and `offset` query params replaced.
+fn page_url(req: &HttpRequest, limit: usize, offset: usize) -> String {
+    let mut query_params =
+        serde_urlencoded::from_str::<Vec<(String, String)>>(req.query_string()).unwrap_or_default();
+    query_params.retain(|(key, _)| key != "limit" && key != "offset");
+    query_params.push(("limit".into(), limit.to_string()));
+    query_params.push(("offset".into(), offset.to_string()));
+
+    let query_string = serde_urlencoded::to_string(query_params).unwrap_or_default();
+    format!("{}?{query_string}", req.path())
+}
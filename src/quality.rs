@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+
+use chrono::{Duration, NaiveDate};
+use serde_json::Value;
+
+use crate::{
+    fields::METRICS,
+    types::{
+        DailyCase, DailyCases, DailyRevision, QualityCounts, QualityFlag, QualityFlagKind,
+        QualityReport,
+    },
+};
+
+/// Number of days on each side of a daily case it's compared with when looking for outliers.
+const OUTLIER_NEIGHBOURS: usize = 7;
+
+/// How many scaled median absolute deviations a value may be away from the median of its
+/// neighbours before it's flagged as an outlier.
+const OUTLIER_THRESHOLD: f64 = 6.0;
+
+impl DailyCases {
+    /// Flag gaps, negative values, and outliers, ordered by date.
+    ///
+    /// Outliers are values that deviate from the median of the surrounding days by more than
+    /// [`OUTLIER_THRESHOLD`] times their scaled median absolute deviation, which catches
+    /// batch-reporting spikes without flagging the steep slopes of a wave.
+    pub fn quality_flags(&self) -> Vec<QualityFlag> {
+        let mut flags = Vec::new();
+
+        for (first_missing, last_missing) in self.gaps() {
+            let missing_days = (last_missing - first_missing).num_days() + 1;
+
+            flags.push(QualityFlag {
+                date: (last_missing + Duration::days(1)).to_string(),
+                kind: QualityFlagKind::Gap,
+                metric: None,
+                detail: format!("{missing_days} day(s) missing before this date"),
+            });
+        }
+
+        for daily in &self.0 {
+            for (metric, value) in METRICS.iter().zip(daily.metrics()) {
+                if value < 0 {
+                    flags.push(QualityFlag {
+                        date: daily.date().to_string(),
+                        kind: QualityFlagKind::Negative,
+                        metric: Some(metric.to_string()),
+                        detail: format!("Negative value {value}"),
+                    });
+                }
+            }
+        }
+
+        for (index, daily) in self.0.iter().enumerate() {
+            let neighbours = self.0[index.saturating_sub(OUTLIER_NEIGHBOURS)..index]
+                .iter()
+                .chain(self.0.iter().skip(index + 1).take(OUTLIER_NEIGHBOURS))
+                .map(DailyCase::metrics)
+                .collect::<Vec<_>>();
+
+            if neighbours.len() < OUTLIER_NEIGHBOURS {
+                continue;
+            }
+
+            for (position, (metric, value)) in METRICS.iter().zip(daily.metrics()).enumerate() {
+                let mut values = neighbours
+                    .iter()
+                    .map(|values| values[position] as f64)
+                    .collect::<Vec<_>>();
+                let typical = median(&mut values);
+                let mut deviations = values
+                    .iter()
+                    .map(|value| (value - typical).abs())
+                    .collect::<Vec<_>>();
+                // Scaled to be comparable with a standard deviation, and at least 1 so that
+                // flat stretches of small values don't turn every change into an outlier.
+                let deviation = (median(&mut deviations) * 1.4826).max(1.0);
+
+                if (value as f64 - typical).abs() > OUTLIER_THRESHOLD * deviation {
+                    flags.push(QualityFlag {
+                        date: daily.date().to_string(),
+                        kind: QualityFlagKind::Outlier,
+                        metric: Some(metric.to_string()),
+                        detail: format!(
+                            "Value {value} deviates from the median {typical} of the surrounding days"
+                        ),
+                    });
+                }
+            }
+        }
+
+        flags.sort_by(|a, b| (&a.date, a.kind).cmp(&(&b.date, b.kind)));
+        flags
+    }
+
+    /// Report every data-quality flag of the daily cases, along with the `revisions` of the
+    /// source API's latest update.
+    pub fn quality_report(&self, revisions: &[DailyRevision]) -> QualityReport {
+        let mut flags = self.quality_flags();
+        flags.extend(revision_flags(revisions));
+        flags.sort_by(|a, b| (&a.date, a.kind).cmp(&(&b.date, b.kind)));
+
+        let missing_days = self
+            .gaps()
+            .into_iter()
+            .map(|(first_missing, last_missing)| {
+                (last_missing - first_missing).num_days() as u32 + 1
+            })
+            .sum();
+
+        let counts = flags
+            .iter()
+            .fold(QualityCounts::default(), |mut counts, flag| {
+                match flag.kind {
+                    QualityFlagKind::Gap => counts.gap += 1,
+                    QualityFlagKind::Negative => counts.negative += 1,
+                    QualityFlagKind::Outlier => counts.outlier += 1,
+                    QualityFlagKind::Revision => counts.revision += 1,
+                }
+                counts
+            });
+
+        QualityReport {
+            days: self.0.len() as u32,
+            missing_days,
+            counts,
+            flags,
+        }
+    }
+}
+
+/// Flag the metrics that the source API's latest update revised, as found in the snapshot history.
+pub fn revision_flags(revisions: &[DailyRevision]) -> Vec<QualityFlag> {
+    revisions
+        .iter()
+        .map(|revision| QualityFlag {
+            date: revision.date.clone(),
+            kind: QualityFlagKind::Revision,
+            metric: Some(revision.metric.clone()),
+            detail: format!(
+                "Revised from {} to {} since the previous snapshot",
+                revision.before, revision.after
+            ),
+        })
+        .collect()
+}
+
+/// Add a `flags` list to every daily case in `value`, which is either a single serialized
+/// daily case or a list of them.
+pub fn annotate(value: &mut Value, flags: &[QualityFlag]) {
+    let flags_by_date = flags.iter().fold(BTreeMap::new(), |mut by_date, flag| {
+        by_date
+            .entry(flag.date.as_str())
+            .or_insert_with(Vec::new)
+            .push(flag);
+        by_date
+    });
+
+    let annotate_item = |item: &mut Value| {
+        if let Value::Object(object) = item {
+            let date = ["year", "month", "day"]
+                .map(|key| object.get(key).and_then(Value::as_u64).unwrap_or_default() as u32);
+            let date = NaiveDate::from_ymd_opt(date[0] as i32, date[1], date[2])
+                .map(|date| date.to_string())
+                .unwrap_or_default();
+
+            let flags = flags_by_date
+                .get(date.as_str())
+                .map(|flags| serde_json::to_value(flags).unwrap_or_default())
+                .unwrap_or_else(|| Value::Array(Vec::new()));
+
+            object.insert("flags".into(), flags);
+        }
+    };
+
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(annotate_item),
+        item => annotate_item(item),
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let middle = values.len() / 2;
+
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
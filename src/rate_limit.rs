@@ -0,0 +1,206 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use tokio::sync::OnceCell;
+use tracing::Instrument;
+
+use crate::config::{RateLimitBackend, RateLimitConfig};
+
+/// Header a client's API key is read from.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Number of buckets kept in memory before the full ones, which are as good as new, are dropped.
+/// The others are kept, so that the throttled clients stay throttled.
+pub const MAX_MEMORY_BUCKETS: usize = 10_000;
+
+/// Take a token from the bucket stored at `KEYS[1]`, refilling it first.<br>
+/// Returns whether a token was taken, and the tokens left as a string since Lua numbers are
+/// truncated to integers when returned.
+const TAKE_TOKEN_SCRIPT: &str = r"
+local capacity = tonumber(ARGV[1])
+local tokens_per_ms = tonumber(ARGV[2])
+local now_ms = tonumber(ARGV[3])
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at_ms')
+local tokens = tonumber(bucket[1]) or capacity
+local updated_at_ms = tonumber(bucket[2]) or now_ms
+tokens = math.min(capacity, tokens + math.max(0, now_ms - updated_at_ms) * tokens_per_ms)
+
+local taken = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    taken = 1
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at_ms', now_ms)
+redis.call('PEXPIRE', KEYS[1], math.ceil((capacity - tokens) / tokens_per_ms) + 1000)
+
+return {taken, tostring(tokens)}
+";
+
+/// Bucket of a client, holding up to `burst` tokens and refilled continuously.
+/// Every request takes a token, and is rejected when there's none left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenBucket {
+    pub tokens: f64,
+    pub updated_at_ms: i64,
+}
+
+/// Outcome of a request's attempt to take a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    /// Capacity of the bucket.
+    pub limit: u32,
+    /// Tokens left, as a whole number.
+    pub remaining: u32,
+    /// Seconds until the bucket is full again.
+    pub reset_secs: u64,
+    /// Seconds until a token is available, for rejected requests.
+    pub retry_after_secs: Option<u64>,
+}
+
+pub enum RateLimiter {
+    Memory {
+        config: RateLimitConfig,
+        buckets: Mutex<HashMap<String, TokenBucket>>,
+    },
+    Redis {
+        config: RateLimitConfig,
+        client: redis::Client,
+        /// Shared by every request, connected on the first one and reconnected when it drops.
+        connection: Box<OnceCell<ConnectionManager>>,
+        script: redis::Script,
+    },
+}
+
+impl TokenBucket {
+    pub fn full(capacity: u32, now_ms: i64) -> Self {
+        Self {
+            tokens: capacity as f64,
+            updated_at_ms: now_ms,
+        }
+    }
+
+    /// Tokens in the bucket at `now_ms`, once refilled.
+    pub fn tokens_at(&self, capacity: u32, tokens_per_ms: f64, now_ms: i64) -> f64 {
+        let elapsed_ms = (now_ms - self.updated_at_ms).max(0) as f64;
+        (self.tokens + elapsed_ms * tokens_per_ms).min(capacity as f64)
+    }
+
+    /// Refill the bucket up to `now_ms`, then take a token if there's one.
+    pub fn take(&mut self, capacity: u32, tokens_per_ms: f64, now_ms: i64) -> bool {
+        self.tokens = self.tokens_at(capacity, tokens_per_ms, now_ms);
+        self.updated_at_ms = now_ms;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl RateLimitDecision {
+    fn new(allowed: bool, tokens: f64, capacity: u32, tokens_per_ms: f64) -> Self {
+        let secs_until = |target: f64| ((target - tokens).max(0.0) / tokens_per_ms / 1000.0).ceil();
+
+        Self {
+            allowed,
+            limit: capacity,
+            remaining: tokens.floor() as u32,
+            reset_secs: secs_until(capacity as f64) as u64,
+            retry_after_secs: (!allowed).then(|| (secs_until(1.0) as u64).max(1)),
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig, redis_url: &str) -> Result<Self, String> {
+        match config.backend {
+            RateLimitBackend::Memory => Ok(Self::Memory {
+                config,
+                buckets: Mutex::new(HashMap::new()),
+            }),
+            RateLimitBackend::Redis => Ok(Self::Redis {
+                config,
+                client: redis::Client::open(redis_url).map_err(|err| err.to_string())?,
+                connection: Box::default(),
+                script: redis::Script::new(TAKE_TOKEN_SCRIPT),
+            }),
+        }
+    }
+
+    pub fn config(&self) -> &RateLimitConfig {
+        match self {
+            Self::Memory { config, .. } | Self::Redis { config, .. } => config,
+        }
+    }
+
+    /// Take a token from the bucket of `client`.<br>
+    /// When Redis is unavailable the request is let through, the limits aren't worth an outage.
+    pub async fn check(&self, client: &str) -> RateLimitDecision {
+        let config = self.config();
+        let capacity = config.burst;
+        let tokens_per_ms = config.requests_per_minute as f64 / 60_000.0;
+        let now_ms = Utc::now().timestamp_millis();
+
+        let (allowed, tokens) = match self {
+            Self::Memory { buckets, .. } => {
+                let mut buckets = buckets.lock().unwrap_or_else(|err| err.into_inner());
+                let is_new = !buckets.contains_key(client);
+                if is_new && buckets.len() >= MAX_MEMORY_BUCKETS {
+                    buckets.retain(|_, bucket| {
+                        bucket.tokens_at(capacity, tokens_per_ms, now_ms) < capacity as f64
+                    });
+                }
+
+                if is_new && buckets.len() >= MAX_MEMORY_BUCKETS {
+                    // Like an unavailable Redis, too many clients at once aren't worth an outage.
+                    tracing::warn!("Too many clients to rate limit, letting a new one through");
+                    let mut bucket = TokenBucket::full(capacity, now_ms);
+                    (bucket.take(capacity, tokens_per_ms, now_ms), bucket.tokens)
+                } else {
+                    let bucket = buckets
+                        .entry(client.to_string())
+                        .or_insert_with(|| TokenBucket::full(capacity, now_ms));
+                    (bucket.take(capacity, tokens_per_ms, now_ms), bucket.tokens)
+                }
+            }
+            Self::Redis {
+                client: redis_client,
+                connection,
+                script,
+                ..
+            } => {
+                let taken = async {
+                    let mut redis_conn = connection
+                        .get_or_try_init(|| ConnectionManager::new(redis_client.clone()))
+                        .await?
+                        .clone();
+                    script
+                        .key(format!("rate_limit:{client}"))
+                        .arg(capacity)
+                        .arg(tokens_per_ms)
+                        .arg(now_ms)
+                        .invoke_async::<_, (i32, String)>(&mut redis_conn)
+                        .await
+                }
+                .instrument(tracing::info_span!("redis_rate_limit", client))
+                .await;
+
+                match taken {
+                    Ok((taken, tokens)) => (taken == 1, tokens.parse().unwrap_or_default()),
+                    Err(err) => {
+                        tracing::warn!(error = %err, "Failed rate limiting with Redis");
+                        (true, capacity as f64)
+                    }
+                }
+            }
+        };
+
+        RateLimitDecision::new(allowed, tokens, capacity, tokens_per_ms)
+    }
+}
@@ -0,0 +1,184 @@
+use std::future::{ready, Ready};
+
+use actix_web::{
+    dev::Payload,
+    error::ErrorNotAcceptable,
+    http::header::{HeaderMap, ACCEPT},
+    FromRequest, HttpRequest, HttpResponse, HttpResponseBuilder,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Serialization format of an endpoint's response, negotiated from the `Accept` header.
+///
+/// Use it as an extractor in a handler and build the response with [`ResponseFormat::respond`].
+/// A missing `Accept` header, or one that only contains wildcards, resolves to JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    Csv,
+    MessagePack,
+    NdJson,
+}
+
+impl ResponseFormat {
+    /// Pick the format with the highest quality value out of the given `Accept` header.<br>
+    /// Returns `None` when the client doesn't accept any of the supported formats.
+    pub fn negotiate(headers: &HeaderMap) -> Option<Self> {
+        let accept = match headers.get(ACCEPT).and_then(|value| value.to_str().ok()) {
+            Some(accept) if !accept.trim().is_empty() => accept,
+            _ => return Some(Self::Json),
+        };
+
+        let mut candidates = accept
+            .split(',')
+            .enumerate()
+            .filter_map(|(position, media_range)| {
+                let mut parts = media_range.split(';').map(str::trim);
+                let format = Self::from_media_type(parts.next()?)?;
+                let quality = parts
+                    .find_map(|param| param.strip_prefix("q="))
+                    .map(|q| q.parse::<f32>().unwrap_or(0.0))
+                    .unwrap_or(1.0);
+
+                (quality > 0.0).then_some((format, quality, position))
+            })
+            .collect::<Vec<_>>();
+
+        // Highest quality wins, ties are resolved by the order in which the client listed them.
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.2.cmp(&b.2)));
+        candidates.first().map(|(format, _, _)| *format)
+    }
+
+    fn from_media_type(media_type: &str) -> Option<Self> {
+        match media_type.to_ascii_lowercase().as_str() {
+            "*/*" | "application/*" | "application/json" => Some(Self::Json),
+            "text/*" | "text/csv" => Some(Self::Csv),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(Self::MessagePack)
+            }
+            "application/x-ndjson" | "application/ndjson" | "application/jsonlines" => {
+                Some(Self::NdJson)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::MessagePack => "application/msgpack",
+            Self::NdJson => "application/x-ndjson",
+        }
+    }
+
+    /// Short, stable identifier of the format, e.g. to be used as a part of a cache key.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+            Self::MessagePack => "msgpack",
+            Self::NdJson => "ndjson",
+        }
+    }
+
+    pub fn serialize<T: Serialize>(&self, data: &T) -> Result<Vec<u8>, String> {
+        let serialized = match self {
+            Self::Json => serde_json::to_vec(data).map_err(|err| err.to_string()),
+            Self::MessagePack => rmp_serde::to_vec_named(data).map_err(|err| err.to_string()),
+            Self::NdJson => {
+                let value = serde_json::to_value(data).map_err(|err| err.to_string())?;
+                to_ndjson(value)
+            }
+            Self::Csv => {
+                let value = serde_json::to_value(data).map_err(|err| err.to_string())?;
+                to_csv(value)
+            }
+        };
+
+        serialized.map_err(|err| format!("Failed serializing response: {err}"))
+    }
+
+    /// Serialize `data` into a `200 OK` response.
+    pub fn respond<T: Serialize>(&self, data: &T) -> Result<HttpResponse, String> {
+        self.respond_with(HttpResponse::Ok(), data)
+    }
+
+    /// Serialize `data` into a response created from the given builder,
+    /// useful when the response needs extra headers.
+    pub fn respond_with<T: Serialize>(
+        &self,
+        mut builder: HttpResponseBuilder,
+        data: &T,
+    ) -> Result<HttpResponse, String> {
+        let body = self.serialize(data)?;
+        Ok(builder.content_type(self.content_type()).body(body))
+    }
+}
+
+impl FromRequest for ResponseFormat {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Self::negotiate(req.headers()).ok_or_else(|| {
+            ErrorNotAcceptable(
+                "Supported formats are application/json, text/csv, application/msgpack, and application/x-ndjson.",
+            )
+        }))
+    }
+}
+
+/// One JSON document per line, arrays are split into one line per item.
+fn to_ndjson(value: Value) -> Result<Vec<u8>, String> {
+    let items = match value {
+        Value::Array(items) => items,
+        other => vec![other],
+    };
+
+    let mut buffer = Vec::new();
+    for item in items {
+        serde_json::to_writer(&mut buffer, &item).map_err(|err| err.to_string())?;
+        buffer.push(b'\n');
+    }
+
+    Ok(buffer)
+}
+
+/// Objects become rows, the header is taken from the keys of the first object.
+fn to_csv(value: Value) -> Result<Vec<u8>, String> {
+    let rows = match value {
+        Value::Array(rows) => rows,
+        other => vec![other],
+    };
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    if let Some(Value::Object(first_row)) = rows.first() {
+        writer
+            .write_record(first_row.keys())
+            .map_err(|err| err.to_string())?;
+    }
+
+    for row in rows {
+        let fields = match row {
+            Value::Object(row) => row.into_iter().map(|(_, field)| field).collect(),
+            other => vec![other],
+        };
+
+        writer
+            .write_record(fields.iter().map(csv_field))
+            .map_err(|err| err.to_string())?;
+    }
+
+    writer.into_inner().map_err(|err| err.to_string())
+}
+
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(value) => value.clone(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,29 @@
+use actix_web::{
+    error::{ErrorInternalServerError, ErrorNotFound},
+    web, HttpResponse,
+};
+
+use crate::{auth::ApiKeys, response::ResponseFormat};
+
+/// Inspect the usage of the API keys, with a key allowed to access `admin`.
+#[utoipa::path(
+    get,
+    path = "/admin/usage",
+    tag = "Monitoring",
+    responses(
+        (status = 200, description = "Success inspecting the usage of the API keys.", body = [KeyUsage]),
+        (status = 401, description = "The API key is missing or invalid.", body = String),
+        (status = 403, description = "The API key isn't allowed to access `admin`.", body = String),
+        (status = 404, description = "Authentication is disabled.", body = String),
+    )
+)]
+pub async fn key_usage(
+    format: ResponseFormat,
+    api_keys: Option<web::Data<ApiKeys>>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let api_keys = api_keys.ok_or_else(|| ErrorNotFound("Authentication is disabled"))?;
+
+    format
+        .respond(&api_keys.usage())
+        .map_err(ErrorInternalServerError)
+}
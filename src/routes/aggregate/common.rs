@@ -0,0 +1,112 @@
+pub mod errors {
+    use actix_web::{HttpResponse, ResponseError};
+    use std::fmt::Debug;
+
+    #[derive(Debug, derive_more::Display)]
+    pub enum AggregateEndpointError {
+        #[display(fmt = "{}", _0)]
+        BadRequest(String),
+        #[display(fmt = "{}", _0)]
+        UnexpectedError(String),
+        #[display(fmt = "{}", _0)]
+        ResourceNotFound(String),
+    }
+
+    impl From<reqwest::Error> for AggregateEndpointError {
+        fn from(err: reqwest::Error) -> Self {
+            Self::UnexpectedError(err.to_string())
+        }
+    }
+
+    impl ResponseError for AggregateEndpointError {
+        fn error_response(&self) -> HttpResponse {
+            let mut http_response = match self {
+                AggregateEndpointError::BadRequest(_) => HttpResponse::BadRequest(),
+                AggregateEndpointError::ResourceNotFound(_) => HttpResponse::NotFound(),
+                _ => HttpResponse::InternalServerError(),
+            };
+
+            http_response.body(self.to_string())
+        }
+    }
+}
+
+pub mod types {
+    use utoipa::IntoParams;
+
+    use super::errors::AggregateEndpointError;
+    use crate::date_range::{DateBound, DateRange, Period};
+
+    /// Longest bucket, already longer than any range of data.
+    pub const MAX_BUCKET_YEARS: u32 = 100;
+
+    #[derive(serde::Deserialize, Debug, IntoParams)]
+    pub struct QueryParams {
+        /// First day of the range, in ISO 8601 format (YYYY-MM-DD) or relative to the latest date
+        /// in the data (today-N). Defaults to the earliest date in the data.
+        #[param(example = "2021-06-15")]
+        pub since: Option<String>,
+        /// Last day of the range, in ISO 8601 format (YYYY-MM-DD) or relative to the latest date
+        /// in the data (today-N). Defaults to the latest date in the data.
+        #[param(example = "2021-08-10")]
+        pub upto: Option<String>,
+        /// Only the given period up to the latest date in the data,
+        /// in days (30d), weeks (12w), months (6m), or years (1y).
+        #[param(example = "30d")]
+        pub last: Option<String>,
+        /// Split the range into buckets of the given size, in days (10d), weeks (2w), months (3m),
+        /// or years (1y). Returns a single record for the whole range by default.
+        #[param(example = "10d")]
+        pub bucket: Option<String>,
+        /// Comma separated metrics to return, any of `positive`, `recovered`, `deaths`, and `active`.
+        /// Defaults to all metrics.
+        #[param(example = "positive,deaths")]
+        pub fields: Option<String>,
+    }
+
+    impl QueryParams {
+        pub fn date_range(&self) -> Result<DateRange, AggregateEndpointError> {
+            let parse_bound = |bound: &Option<String>| {
+                bound
+                    .as_deref()
+                    .map(DateBound::parse)
+                    .transpose()
+                    .map_err(AggregateEndpointError::BadRequest)
+            };
+
+            Ok(DateRange {
+                since: parse_bound(&self.since)?,
+                upto: parse_bound(&self.upto)?,
+                last: parse_period(&self.last)?,
+            })
+        }
+
+        /// The size of the buckets, at most [`MAX_BUCKET_YEARS`] years.
+        pub fn bucket_size(&self) -> Result<Option<Period>, AggregateEndpointError> {
+            let bucket_size = parse_period(&self.bucket)?;
+
+            let is_too_long = match bucket_size {
+                Some(Period::Days(days)) => days > MAX_BUCKET_YEARS * 366,
+                Some(Period::Weeks(weeks)) => weeks > MAX_BUCKET_YEARS * 53,
+                Some(Period::Months(months)) => months > MAX_BUCKET_YEARS * 12,
+                Some(Period::Years(years)) => years > MAX_BUCKET_YEARS,
+                None => false,
+            };
+            if is_too_long {
+                return Err(AggregateEndpointError::BadRequest(format!(
+                    "Buckets are at most {MAX_BUCKET_YEARS} years long"
+                )));
+            }
+
+            Ok(bucket_size)
+        }
+    }
+
+    fn parse_period(period: &Option<String>) -> Result<Option<Period>, AggregateEndpointError> {
+        period
+            .as_deref()
+            .map(Period::parse)
+            .transpose()
+            .map_err(AggregateEndpointError::BadRequest)
+    }
+}
@@ -0,0 +1,61 @@
+use super::{common::types::QueryParams, errors::AggregateEndpointError};
+use crate::{
+    fields::Fields, response::ResponseFormat, storage::SharedStorage, utils::ingest_into_storage,
+};
+
+use actix_web::{get, web, HttpResponse};
+use utoipa::IntoParams;
+
+/// Sum up daily cases over an arbitrary date range, optionally split into buckets.
+///
+/// Returns a single record by default, or a list of records when `bucket` is given.
+#[utoipa::path(
+    context_path = "/aggregate",
+    tag = "Data",
+    responses(
+        (status = 200, description = "Success getting the data.", body = AggregatedCase),
+        (status = 400, description = "Malformed date range, bucket size, or fields.", body = String),
+        (status = 404, description = "There are no cases within the given range.", body = String),
+        (status = 500, description = "Something went wrong during the processing.", body = String),
+    )
+)]
+#[get("")]
+pub async fn aggregate(
+    format: ResponseFormat,
+    params: web::Query<QueryParams>,
+    storage: web::Data<SharedStorage>,
+) -> Result<HttpResponse, AggregateEndpointError> {
+    let range = params.date_range()?;
+    let bucket_size = params.bucket_size()?;
+    let fields = Fields::from_query_param(params.fields.as_deref())
+        .map_err(AggregateEndpointError::BadRequest)?;
+
+    ingest_into_storage(&storage)
+        .await
+        .map_err(AggregateEndpointError::UnexpectedError)?;
+    let (since, upto, daily_cases) = storage
+        .query(move |storage| {
+            let (since, upto) = range.resolve(storage.latest_date()?);
+            Ok((since, upto, storage.daily_cases(since, upto)?))
+        })
+        .await
+        .map_err(AggregateEndpointError::UnexpectedError)?;
+
+    let aggregated_cases = daily_cases.aggregate(since, upto, bucket_size);
+
+    if aggregated_cases.is_empty() {
+        return Err(AggregateEndpointError::ResourceNotFound(
+            "There are no cases within the given range".into(),
+        ));
+    }
+
+    let body = match bucket_size {
+        Some(_) => fields.select(&aggregated_cases),
+        None => fields.select(&aggregated_cases[0]),
+    }
+    .map_err(AggregateEndpointError::UnexpectedError)?;
+
+    format
+        .respond(&body)
+        .map_err(AggregateEndpointError::UnexpectedError)
+}
@@ -0,0 +1,111 @@
+pub mod errors {
+    use actix_web::{HttpResponse, ResponseError};
+    use std::fmt::Debug;
+
+    #[derive(Debug, derive_more::Display)]
+    pub enum CompareEndpointError {
+        #[display(fmt = "{}", _0)]
+        BadRequest(String),
+        #[display(fmt = "{}", _0)]
+        UnexpectedError(String),
+        #[display(fmt = "{}", _0)]
+        ResourceNotFound(String),
+    }
+
+    impl From<reqwest::Error> for CompareEndpointError {
+        fn from(err: reqwest::Error) -> Self {
+            Self::UnexpectedError(err.to_string())
+        }
+    }
+
+    impl ResponseError for CompareEndpointError {
+        fn error_response(&self) -> HttpResponse {
+            let mut http_response = match self {
+                CompareEndpointError::BadRequest(_) => HttpResponse::BadRequest(),
+                CompareEndpointError::ResourceNotFound(_) => HttpResponse::NotFound(),
+                _ => HttpResponse::InternalServerError(),
+            };
+
+            http_response.body(self.to_string())
+        }
+    }
+}
+
+pub mod types {
+    use utoipa::IntoParams;
+
+    use super::errors::CompareEndpointError;
+    use crate::date_range::{Baseline, DateBound, DateRange, Period};
+
+    #[derive(serde::Deserialize, Debug, IntoParams)]
+    pub struct QueryParams {
+        /// First day of the compared range, in ISO 8601 format (YYYY-MM-DD) or relative to
+        /// the latest date in the data (today-N). Defaults to the earliest date in the data.
+        #[param(example = "2021-07-01")]
+        pub since: Option<String>,
+        /// Last day of the compared range, in ISO 8601 format (YYYY-MM-DD) or relative to
+        /// the latest date in the data (today-N). Defaults to the latest date in the data.
+        #[param(example = "2021-07-31")]
+        pub upto: Option<String>,
+        /// Compare the given period up to the latest date in the data,
+        /// in days (30d), weeks (12w), months (6m), or years (1y).
+        #[param(example = "30d")]
+        pub last: Option<String>,
+        /// Baseline to compare against, either `previous` (default) or `year_ago`.
+        /// Can't be combined with `vs_since` and `vs_upto`.
+        #[param(example = "previous")]
+        pub vs: Option<Baseline>,
+        /// First day of an explicit baseline range, in ISO 8601 format (YYYY-MM-DD).
+        #[param(example = "2021-06-01")]
+        pub vs_since: Option<String>,
+        /// Last day of an explicit baseline range, in ISO 8601 format (YYYY-MM-DD).
+        #[param(example = "2021-06-30")]
+        pub vs_upto: Option<String>,
+    }
+
+    /// What the requested range is compared against.
+    pub enum ComparedTo {
+        Baseline(Baseline),
+        Range(DateRange),
+    }
+
+    impl QueryParams {
+        pub fn date_range(&self) -> Result<DateRange, CompareEndpointError> {
+            Ok(DateRange {
+                since: parse_bound(&self.since)?,
+                upto: parse_bound(&self.upto)?,
+                last: self
+                    .last
+                    .as_deref()
+                    .map(Period::parse)
+                    .transpose()
+                    .map_err(CompareEndpointError::BadRequest)?,
+            })
+        }
+
+        pub fn compared_to(&self) -> Result<ComparedTo, CompareEndpointError> {
+            match (self.vs, &self.vs_since, &self.vs_upto) {
+                (vs, None, None) => Ok(ComparedTo::Baseline(vs.unwrap_or(Baseline::Previous))),
+                (None, Some(_), Some(_)) => Ok(ComparedTo::Range(DateRange {
+                    since: parse_bound(&self.vs_since)?,
+                    upto: parse_bound(&self.vs_upto)?,
+                    last: None,
+                })),
+                (Some(_), _, _) => Err(CompareEndpointError::BadRequest(
+                    "vs can't be combined with vs_since and vs_upto".into(),
+                )),
+                _ => Err(CompareEndpointError::BadRequest(
+                    "vs_since and vs_upto must be given together".into(),
+                )),
+            }
+        }
+    }
+
+    fn parse_bound(bound: &Option<String>) -> Result<Option<DateBound>, CompareEndpointError> {
+        bound
+            .as_deref()
+            .map(DateBound::parse)
+            .transpose()
+            .map_err(CompareEndpointError::BadRequest)
+    }
+}
@@ -0,0 +1,88 @@
+use super::{
+    common::types::{ComparedTo, QueryParams},
+    errors::CompareEndpointError,
+};
+use crate::{
+    response::ResponseFormat, storage::SharedStorage, types::CaseComparison,
+    utils::ingest_into_storage,
+};
+
+use actix_web::{get, web, HttpResponse};
+use utoipa::IntoParams;
+
+/// Compare the cases of a date range with a baseline period.
+///
+/// The baseline is either the previous period of the same length, the same period a year before,
+/// or an explicit range given through `vs_since` and `vs_upto`.
+#[utoipa::path(
+    context_path = "/compare",
+    tag = "Data",
+    responses(
+        (status = 200, description = "Success getting the data.", body = CaseComparison),
+        (status = 400, description = "Malformed date ranges or baseline.", body = String),
+        (status = 404, description = "There are no cases within the compared range.", body = String),
+        (status = 500, description = "Something went wrong during the processing.", body = String),
+    )
+)]
+#[get("")]
+pub async fn compare(
+    format: ResponseFormat,
+    params: web::Query<QueryParams>,
+    storage: web::Data<SharedStorage>,
+) -> Result<HttpResponse, CompareEndpointError> {
+    let range = params.date_range()?;
+    let compared_to = params.compared_to()?;
+
+    ingest_into_storage(&storage)
+        .await
+        .map_err(CompareEndpointError::UnexpectedError)?;
+    let daily_cases = storage
+        .query(|storage| storage.daily_cases(None, None))
+        .await
+        .map_err(CompareEndpointError::UnexpectedError)?;
+
+    let latest_date = daily_cases.latest_date();
+    let (since, upto) = range.resolve(latest_date);
+    let (since, upto) = match (since.or(daily_cases.earliest_date()), upto.or(latest_date)) {
+        (Some(since), Some(upto)) if since <= upto => (since, upto),
+        _ => {
+            return Err(CompareEndpointError::ResourceNotFound(
+                "There are no cases within the given range".into(),
+            ))
+        }
+    };
+
+    let (baseline_since, baseline_upto) = match compared_to {
+        ComparedTo::Baseline(baseline) => baseline.of(since, upto).ok_or_else(|| {
+            CompareEndpointError::BadRequest("The baseline is out of the range of dates".into())
+        })?,
+        ComparedTo::Range(baseline_range) => match baseline_range.resolve(latest_date) {
+            (Some(baseline_since), Some(baseline_upto)) if baseline_since <= baseline_upto => {
+                (baseline_since, baseline_upto)
+            }
+            (Some(_), Some(_)) => {
+                return Err(CompareEndpointError::BadRequest(
+                    "vs_since must not be later than vs_upto".into(),
+                ))
+            }
+            _ => {
+                return Err(CompareEndpointError::BadRequest(
+                    "vs_since and vs_upto must be valid dates".into(),
+                ))
+            }
+        },
+    };
+
+    let current = daily_cases.total_between(since, upto);
+    if current.days == 0 {
+        return Err(CompareEndpointError::ResourceNotFound(
+            "There are no cases within the given range".into(),
+        ));
+    }
+
+    let baseline = daily_cases.total_between(baseline_since, baseline_upto);
+
+    format
+        .respond(&CaseComparison::new(current, baseline))
+        .map_err(CompareEndpointError::UnexpectedError)
+}
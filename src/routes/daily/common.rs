@@ -1,5 +1,13 @@
 pub mod types {
-    use actix_web::{HttpResponse, ResponseError};
+    use actix_web::{web, HttpResponse, ResponseError};
+
+    use serde_json::Value;
+
+    use crate::{
+        date_range::DateRange, fields::Fields, fill::Fill, pagination::Pagination, quality,
+        snapshots::SnapshotStore, storage::SharedStorage, types::DailyCases,
+        utils::ingest_into_storage,
+    };
 
     #[derive(Debug, derive_more::Display)]
     pub enum DailyEndpointError {
@@ -28,28 +36,121 @@ pub mod types {
 
     #[derive(Debug, Clone)]
     pub struct DailyQueryParams {
-        pub since: Option<YearMonthDay>,
-        pub upto: Option<YearMonthDay>,
+        pub range: DateRange,
+        pub pagination: Pagination,
+        pub fields: Fields,
+        /// Whether to annotate the daily cases with their data-quality flags.
+        pub flags: bool,
+        /// How to fill the days missing from the series, `None` leaves them out.
+        pub fill: Option<Fill>,
+        /// Id of the snapshot to read the daily cases from, `None` reads the latest data.
+        pub as_of: Option<i64>,
     }
 
-    #[derive(Debug, Clone)]
-    pub struct YearMonthDay {
-        pub year: i32,
-        pub month: u32,
-        pub day: u32,
+    impl DailyQueryParams {
+        /// Storage to query the daily cases from: `storage` once the latest data is ingested.<br>
+        /// For `as_of`, an in-memory one holding the daily cases of the requested snapshot.
+        pub async fn storage(
+            &self,
+            storage: &SharedStorage,
+        ) -> Result<SharedStorage, DailyEndpointError> {
+            let id = match self.as_of {
+                Some(id) => id,
+                None => {
+                    ingest_into_storage(storage)
+                        .await
+                        .map_err(DailyEndpointError::UnexpectedError)?;
+                    return Ok(storage.clone());
+                }
+            };
+
+            web::block(move || SnapshotStore::from_config().storage(id))
+                .await
+                .map_err(|err| DailyEndpointError::UnexpectedError(err.to_string()))?
+                .map_err(DailyEndpointError::UnexpectedError)?
+                .ok_or_else(|| DailyEndpointError::NotFound(format!("There is no snapshot {id}")))
+        }
+
+        /// The daily cases of `storage` within the requested range.
+        pub async fn daily_cases(
+            &self,
+            storage: &SharedStorage,
+        ) -> Result<DailyCases, DailyEndpointError> {
+            let range = self.range.clone();
+
+            storage
+                .query(move |storage| {
+                    let (since, upto) = range.resolve(storage.latest_date()?);
+                    storage.daily_cases(since, upto)
+                })
+                .await
+                .map_err(DailyEndpointError::UnexpectedError)
+        }
+
+        /// Fill the missing days if requested, then serialize the requested page with the selected fields.<br>
+        /// Returns the page along with the number of items before pagination.
+        pub fn select_page(&self, daily_cases: DailyCases) -> Result<(Value, usize), String> {
+            match self.fill {
+                Some(fill) => {
+                    let page = self.pagination.paginate(daily_cases.fill(fill));
+                    Ok((self.fields.select(&page.items)?, page.total))
+                }
+                None => {
+                    let page = self.pagination.paginate(daily_cases.0);
+                    Ok((self.fields.select(&page.items)?, page.total))
+                }
+            }
+        }
+
+        /// Add the data-quality flags to the serialized daily cases if they were requested.<br>
+        /// The flags are found across every daily case of `storage`, along with the revisions of
+        /// the snapshot they're read from.
+        pub async fn annotate_flags(
+            &self,
+            storage: &SharedStorage,
+            body: &mut Value,
+        ) -> Result<(), DailyEndpointError> {
+            if !self.flags {
+                return Ok(());
+            }
+
+            let daily_cases = storage
+                .query(|storage| storage.daily_cases(None, None))
+                .await
+                .map_err(DailyEndpointError::UnexpectedError)?;
+            let as_of = self.as_of;
+            let revisions =
+                web::block(move || SnapshotStore::from_config().latest_revisions(as_of))
+                    .await
+                    .map_err(|err| DailyEndpointError::UnexpectedError(err.to_string()))?
+                    .map_err(DailyEndpointError::UnexpectedError)?;
+
+            let mut flags = daily_cases.quality_flags();
+            flags.extend(quality::revision_flags(&revisions));
+            quality::annotate(body, &flags);
+
+            Ok(())
+        }
     }
 }
 
 pub mod middleware {
-    use super::types::{DailyQueryParams, YearMonthDay};
-    use crate::types::QueryParams;
+    use super::types::DailyQueryParams;
+    use crate::{
+        date_range::{DateBound, DateRange, Period},
+        fields::Fields,
+        pagination::Pagination,
+        types::QueryParams,
+    };
 
     use actix_web::{
         body::MessageBody,
         dev::{ServiceRequest, ServiceResponse},
+        error::ErrorBadRequest,
         HttpMessage,
     };
     use actix_web_lab::middleware::Next;
+    use chrono::NaiveDate;
     use std::num::ParseIntError;
 
     pub async fn filter_malformed_query_params(
@@ -59,52 +160,56 @@ pub mod middleware {
         let query_string = req.query_string();
         let query_params = serde_urlencoded::from_str::<QueryParams>(query_string)?;
 
-        let mut daily_query_params = DailyQueryParams {
-            since: None,
-            upto: None,
+        let range = DateRange {
+            since: parse_date_bound(query_params.since.as_deref()).map_err(ErrorBadRequest)?,
+            upto: parse_date_bound(query_params.upto.as_deref()).map_err(ErrorBadRequest)?,
+            last: query_params
+                .last
+                .as_deref()
+                .map(Period::parse)
+                .transpose()
+                .map_err(ErrorBadRequest)?,
         };
 
-        if let Some(since) = query_params.since {
-            let splitted_params = since.split('-').collect::<Vec<_>>();
-
-            if splitted_params.len() == 3 {
-                let splitted_params = splitted_params
-                    .iter()
-                    .map(|x| x.parse::<i32>())
-                    .collect::<Result<Vec<i32>, ParseIntError>>();
-
-                if let Ok(params) = splitted_params {
-                    let (year, month, day) = (params[0], params[1], params[2]);
-                    daily_query_params.since = Some(YearMonthDay {
-                        year,
-                        month: month as u32,
-                        day: day as u32,
-                    });
-                }
-            }
+        let daily_query_params = DailyQueryParams {
+            range,
+            pagination: Pagination::from_query_params(&query_params).map_err(ErrorBadRequest)?,
+            fields: Fields::from_query_param(query_params.fields.as_deref())
+                .map_err(ErrorBadRequest)?,
+            flags: query_params.flags.unwrap_or_default(),
+            fill: query_params.fill,
+            as_of: query_params.as_of,
+        };
+
+        req.extensions_mut().insert(daily_query_params);
+        next.call(req).await
+    }
+
+    /// Parse either a relative date (`today-N`) or a date in ISO 8601 format (YYYY-MM-DD).<br>
+    /// Malformed ISO 8601 dates are ignored, malformed relative dates are rejected.
+    fn parse_date_bound(value: Option<&str>) -> Result<Option<DateBound>, String> {
+        let value = match value {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        if let Some(relative) = DateBound::parse_relative(value) {
+            return relative.map(Some);
         }
 
-        if let Some(upto) = query_params.upto {
-            let splitted_params = upto.split('-').collect::<Vec<_>>();
-
-            if splitted_params.len() == 3 {
-                let splitted_params = splitted_params
-                    .iter()
-                    .map(|x| x.parse::<i32>())
-                    .collect::<Result<Vec<i32>, ParseIntError>>();
-
-                if let Ok(params) = splitted_params {
-                    let (year, month, day) = (params[0], params[1], params[2]);
-                    daily_query_params.upto = Some(YearMonthDay {
-                        year,
-                        month: month as u32,
-                        day: day as u32,
-                    });
-                }
-            }
+        let splitted_params = value.split('-').collect::<Vec<_>>();
+        if splitted_params.len() != 3 {
+            return Ok(None);
         }
 
-        req.extensions_mut().insert(daily_query_params);
-        next.call(req).await
+        let splitted_params = splitted_params
+            .iter()
+            .map(|x| x.parse::<i32>())
+            .collect::<Result<Vec<i32>, ParseIntError>>();
+
+        Ok(splitted_params.ok().and_then(|params| {
+            let (year, month, day) = (params[0], params[1], params[2]);
+            NaiveDate::from_ymd_opt(year, month as u32, day as u32).map(DateBound::Absolute)
+        }))
     }
 }
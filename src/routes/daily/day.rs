@@ -1,7 +1,8 @@
-use super::types::DailyEndpointError;
-use crate::utils::fetch_data_from_source_api;
+use super::types::{DailyEndpointError, DailyQueryParams};
+use crate::{response::ResponseFormat, storage::SharedStorage, types::DailyCases};
 
 use actix_web::{get, web, HttpResponse};
+use chrono::NaiveDate;
 
 /// Get a specific day's case.
 #[utoipa::path(
@@ -23,6 +24,24 @@ use actix_web::{get, web, HttpResponse};
             description = "Selected day.",
             example = 26
         ),
+        (
+            "fields" = Option<String>,
+            query,
+            description = "Comma separated metrics to return, any of `positive`, `recovered`, `deaths`, and `active`. Defaults to all metrics.",
+            example = "positive,deaths"
+        ),
+        (
+            "flags" = Option<bool>,
+            query,
+            description = "Annotate each daily case with its data-quality flags, i.e. gaps, negative values, outliers, and revisions.",
+            example = true
+        ),
+        (
+            "as_of" = Option<i64>,
+            query,
+            description = "Id of a snapshot to read the daily cases from, as they were when it was fetched. Defaults to the latest data.",
+            example = 1661958000000_i64
+        ),
     ),
     responses(
         (status = 200, description = "Success getting the data.", body = DailyCase),
@@ -32,16 +51,34 @@ use actix_web::{get, web, HttpResponse};
 )]
 #[get("/{year}/{month}/{day}")]
 pub async fn specific_day(
+    format: ResponseFormat,
+    params: web::ReqData<DailyQueryParams>,
     path: web::Path<(i32, i32, i32)>,
+    storage: web::Data<SharedStorage>,
 ) -> Result<HttpResponse, DailyEndpointError> {
     let (selected_year, selected_month, selected_day) = path.into_inner();
 
-    let daily_case = fetch_data_from_source_api()
-        .await
-        .map_err(DailyEndpointError::UnexpectedError)?
-        .to_daily()
+    let storage = params.storage(&storage).await?;
+    let daily_cases =
+        match NaiveDate::from_ymd_opt(selected_year, selected_month as u32, selected_day as u32) {
+            Some(date) => storage
+                .query(move |storage| storage.daily_cases(Some(date), Some(date)))
+                .await
+                .map_err(DailyEndpointError::UnexpectedError)?,
+            None => DailyCases(Vec::new()),
+        };
+
+    let daily_case = daily_cases
         .get_specific_day(selected_year, selected_month, selected_day)
         .map_err(DailyEndpointError::NotFound)?;
 
-    Ok(HttpResponse::Ok().json(daily_case))
+    let mut body = params
+        .fields
+        .select(&daily_case)
+        .map_err(DailyEndpointError::UnexpectedError)?;
+    params.annotate_flags(&storage, &mut body).await?;
+
+    format
+        .respond(&body)
+        .map_err(DailyEndpointError::UnexpectedError)
 }
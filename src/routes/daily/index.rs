@@ -1,8 +1,7 @@
 use super::types::{DailyEndpointError, DailyQueryParams};
-use crate::utils::fetch_data_from_source_api;
+use crate::{response::ResponseFormat, storage::SharedStorage};
 
-use actix_web::{get, web, HttpResponse};
-use chrono::NaiveDate;
+use actix_web::{get, web, HttpRequest, HttpResponse};
 
 /// Get all daily cases.
 #[utoipa::path(
@@ -12,63 +11,87 @@ use chrono::NaiveDate;
         (
             "since" = Option<String>,
             query,
-            description = "In ISO 8601 format (YYYY-MM-DD).",
+            description = "In ISO 8601 format (YYYY-MM-DD), or relative to the latest date in the data (today-N).",
             example = "2021-03-03"
         ),
         (
             "upto" = Option<String>,
             query,
-            description = "In ISO 8601 format (YYYY-MM-DD).",
+            description = "In ISO 8601 format (YYYY-MM-DD), or relative to the latest date in the data (today-N).",
             example = "2021-04-01"
-        )
+        ),
+        (
+            "last" = Option<String>,
+            query,
+            description = "Only the given period up to the latest date in the data, in days (30d), weeks (12w), months (6m), or years (1y).",
+            example = "30d"
+        ),
+        (
+            "limit" = Option<usize>,
+            query,
+            description = "Maximum number of items to return.",
+            example = 30
+        ),
+        (
+            "offset" = Option<usize>,
+            query,
+            description = "Number of items to skip.",
+            example = 0
+        ),
+        (
+            "order" = Option<String>,
+            query,
+            description = "Order of the items by date, either `asc` (default) or `desc`.",
+            example = "desc"
+        ),
+        (
+            "fields" = Option<String>,
+            query,
+            description = "Comma separated metrics to return, any of `positive`, `recovered`, `deaths`, and `active`. Defaults to all metrics.",
+            example = "positive,deaths"
+        ),
+        (
+            "flags" = Option<bool>,
+            query,
+            description = "Annotate each daily case with its data-quality flags, i.e. gaps, negative values, outliers, and revisions.",
+            example = true
+        ),
+        (
+            "as_of" = Option<i64>,
+            query,
+            description = "Id of a snapshot to read the daily cases from, as they were when it was fetched. Defaults to the latest data.",
+            example = 1661958000000_i64
+        ),
+        (
+            "fill" = Option<String>,
+            query,
+            description = "Fill the days missing from the source with `zero`, `null`, or `interpolate`d values, filled days are marked with `filled`.",
+            example = "interpolate"
+        ),
     ),
     responses(
         (status = 200, description = "Success getting the data.", body = [DailyCase]),
+        (status = 404, description = "There is no snapshot with the given id.", body = String),
         (status = 500, description = "Something went wrong during the processing.", body = String),
     )
 )]
 #[get("")]
 pub async fn all_days(
+    req: HttpRequest,
+    format: ResponseFormat,
     params: web::ReqData<DailyQueryParams>,
+    storage: web::Data<SharedStorage>,
 ) -> Result<HttpResponse, DailyEndpointError> {
     let params = params.into_inner();
-    let mut daily_cases = fetch_data_from_source_api()
-        .await
-        .map_err(DailyEndpointError::UnexpectedError)?
-        .to_daily();
-
-    if let Some(since) = params.since {
-        daily_cases = daily_cases
-            .0
-            .into_iter()
-            .filter(|daily| {
-                let daily_date = NaiveDate::from_ymd(daily.year, daily.month, daily.day);
-                let since_date = NaiveDate::from_ymd(since.year, since.month, since.day);
-
-                let num_of_days_after_since =
-                    daily_date.signed_duration_since(since_date).num_days();
-
-                num_of_days_after_since >= 0
-            })
-            .collect();
-    }
-
-    if let Some(upto) = params.upto {
-        daily_cases = daily_cases
-            .0
-            .into_iter()
-            .filter(|daily| {
-                let current_daily_date = NaiveDate::from_ymd(daily.year, daily.month, daily.day);
-                let upto_date = NaiveDate::from_ymd(upto.year, upto.month, upto.day);
-
-                let num_of_days_till_upto = current_daily_date
-                    .signed_duration_since(upto_date)
-                    .num_days();
+    let storage = params.storage(&storage).await?;
+    let daily_cases = params.daily_cases(&storage).await?;
 
-                num_of_days_till_upto <= 0
-            })
-            .collect();
-    }
+    let (mut body, total) = params
+        .select_page(daily_cases)
+        .map_err(DailyEndpointError::UnexpectedError)?;
+    params.annotate_flags(&storage, &mut body).await?;
 
-    Ok(HttpResponse::Ok().json(daily_cases.0))
+    format
+        .respond_with(params.pagination.response_builder(&req, total), &body)
+        .map_err(DailyEndpointError::UnexpectedError)
 }
@@ -0,0 +1,62 @@
+use super::types::{DailyEndpointError, DailyQueryParams};
+use crate::{response::ResponseFormat, storage::SharedStorage};
+
+use actix_web::{get, web, HttpResponse};
+
+/// Get the most recent daily case.
+#[utoipa::path(
+    context_path = "/daily",
+    tag = "Data",
+    params(
+        (
+            "fields" = Option<String>,
+            query,
+            description = "Comma separated metrics to return, any of `positive`, `recovered`, `deaths`, and `active`. Defaults to all metrics.",
+            example = "positive,deaths"
+        ),
+        (
+            "flags" = Option<bool>,
+            query,
+            description = "Annotate each daily case with its data-quality flags, i.e. gaps, negative values, outliers, and revisions.",
+            example = true
+        ),
+        (
+            "as_of" = Option<i64>,
+            query,
+            description = "Id of a snapshot to read the daily cases from, as they were when it was fetched. Defaults to the latest data.",
+            example = 1661958000000_i64
+        ),
+    ),
+    responses(
+        (status = 200, description = "Success getting the data.", body = DailyCase),
+        (status = 404, description = "There are no cases yet.", body = String),
+        (status = 500, description = "Something went wrong during the processing.", body = String),
+    )
+)]
+#[get("/latest")]
+pub async fn latest_day(
+    format: ResponseFormat,
+    params: web::ReqData<DailyQueryParams>,
+    storage: web::Data<SharedStorage>,
+) -> Result<HttpResponse, DailyEndpointError> {
+    let storage = params.storage(&storage).await?;
+    let daily_case = storage
+        .query(|storage| {
+            let latest_date = storage.latest_date()?;
+            storage.daily_cases(latest_date, latest_date)
+        })
+        .await
+        .map_err(DailyEndpointError::UnexpectedError)?
+        .get_latest_day()
+        .map_err(DailyEndpointError::NotFound)?;
+
+    let mut body = params
+        .fields
+        .select(&daily_case)
+        .map_err(DailyEndpointError::UnexpectedError)?;
+    params.annotate_flags(&storage, &mut body).await?;
+
+    format
+        .respond(&body)
+        .map_err(DailyEndpointError::UnexpectedError)
+}
@@ -1,8 +1,9 @@
 mod common;
 mod day;
 mod index;
+mod latest;
 mod month;
 mod year;
 
 pub use common::{middleware, types};
-pub use {day::*, index::*, month::*, year::*};
+pub use {day::*, index::*, latest::*, month::*, year::*};
@@ -1,6 +1,8 @@
 use actix_web::HttpResponse;
 use utoipa::Component;
 
+use crate::response::ResponseFormat;
+
 #[derive(serde::Serialize, Component)]
 pub enum ServiceStatus {
     OK,
@@ -29,10 +31,12 @@ pub struct ServiceHealth {
         (status = 500, description = "Something went wrong during the processing.", body = String),
     )
 )]
-pub async fn service_health() -> HttpResponse {
+pub async fn service_health(format: ResponseFormat) -> Result<HttpResponse, actix_web::Error> {
     let data = ServiceHealth {
         status: ServiceStatus::OK,
     };
 
-    HttpResponse::Ok().json(data)
+    format
+        .respond(&data)
+        .map_err(actix_web::error::ErrorInternalServerError)
 }
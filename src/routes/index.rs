@@ -1,4 +1,4 @@
-use crate::utils::fetch_data_from_source_api;
+use crate::{response::ResponseFormat, utils::fetch_data_from_source_api};
 use actix_web::{HttpResponse, ResponseError};
 use serde::{Deserialize, Serialize};
 use utoipa::Component;
@@ -54,7 +54,9 @@ impl ResponseError for SlashEndpointError {
         (status = 500, description = "Something went wrong during the processing.", body = String),
     )
 )]
-pub async fn daily_cases_summary() -> Result<HttpResponse, SlashEndpointError> {
+pub async fn daily_cases_summary(
+    format: ResponseFormat,
+) -> Result<HttpResponse, SlashEndpointError> {
     let resp = fetch_data_from_source_api()
         .await
         .map_err(SlashEndpointError::UnexpectedError)?;
@@ -70,5 +72,7 @@ pub async fn daily_cases_summary() -> Result<HttpResponse, SlashEndpointError> {
         new_active: resp.update.penambahan.jumlah_dirawat,
     };
 
-    Ok(HttpResponse::Ok().json(resp))
+    format
+        .respond(&resp)
+        .map_err(SlashEndpointError::UnexpectedError)
 }
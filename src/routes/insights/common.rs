@@ -0,0 +1,79 @@
+pub mod errors {
+    use actix_web::{HttpResponse, ResponseError};
+    use std::fmt::Debug;
+
+    #[derive(Debug, derive_more::Display)]
+    pub enum InsightsEndpointError {
+        #[display(fmt = "{}", _0)]
+        BadRequest(String),
+        #[display(fmt = "{}", _0)]
+        UnexpectedError(String),
+        #[display(fmt = "{}", _0)]
+        ResourceNotFound(String),
+    }
+
+    impl From<reqwest::Error> for InsightsEndpointError {
+        fn from(err: reqwest::Error) -> Self {
+            Self::UnexpectedError(err.to_string())
+        }
+    }
+
+    impl ResponseError for InsightsEndpointError {
+        fn error_response(&self) -> HttpResponse {
+            let mut http_response = match self {
+                InsightsEndpointError::BadRequest(_) => HttpResponse::BadRequest(),
+                InsightsEndpointError::ResourceNotFound(_) => HttpResponse::NotFound(),
+                _ => HttpResponse::InternalServerError(),
+            };
+
+            http_response.body(self.to_string())
+        }
+    }
+}
+
+pub mod types {
+    use utoipa::IntoParams;
+
+    use super::errors::InsightsEndpointError;
+    use crate::insights::{Metric, WaveOptions};
+
+    #[derive(serde::Deserialize, Debug, IntoParams)]
+    pub struct PeaksQueryParams {
+        /// Metric to detect waves on, any of `positive` (default), `deaths`, and `active`.
+        #[param(example = "positive")]
+        pub metric: Option<Metric>,
+        /// Minimum prominence of a wave's peak, relative to the highest value of the metric.
+        /// Must be greater than 0 and at most 1, defaults to 0.1.
+        #[param(example = 0.1)]
+        pub prominence: Option<f64>,
+        /// Size of the moving average window the metric is smoothed with, in days. Defaults to 7.
+        #[param(example = 7)]
+        pub window: Option<usize>,
+    }
+
+    impl PeaksQueryParams {
+        pub fn wave_options(&self) -> Result<WaveOptions, InsightsEndpointError> {
+            let defaults = WaveOptions::default();
+
+            let prominence = self.prominence.unwrap_or(defaults.prominence);
+            if !(prominence > 0.0 && prominence <= 1.0) {
+                return Err(InsightsEndpointError::BadRequest(
+                    "prominence must be greater than 0 and at most 1".into(),
+                ));
+            }
+
+            let window = self.window.unwrap_or(defaults.window);
+            if window == 0 {
+                return Err(InsightsEndpointError::BadRequest(
+                    "window must be greater than 0".into(),
+                ));
+            }
+
+            Ok(WaveOptions {
+                metric: self.metric.unwrap_or_default(),
+                prominence,
+                window,
+            })
+        }
+    }
+}
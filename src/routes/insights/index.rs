@@ -0,0 +1,52 @@
+use super::{common::types::PeaksQueryParams, errors::InsightsEndpointError};
+use crate::{
+    response::ResponseFormat, storage::SharedStorage, types::Peaks, utils::ingest_into_storage,
+};
+
+use actix_web::{get, web, HttpResponse};
+use utoipa::IntoParams;
+
+/// Get the record highs and the waves of cases across the whole history.
+///
+/// Records are the highest daily, weekly (Monday to Sunday), and monthly values of
+/// `positive`, `deaths`, and `active`. Waves are the peaks of the smoothed `metric`
+/// that stand out by at least the given `prominence`.
+#[utoipa::path(
+    context_path = "/insights",
+    tag = "Data",
+    responses(
+        (status = 200, description = "Success getting the data.", body = Peaks),
+        (status = 400, description = "Malformed metric, prominence, or window.", body = String),
+        (status = 404, description = "There are no cases yet.", body = String),
+        (status = 500, description = "Something went wrong during the processing.", body = String),
+    )
+)]
+#[get("/peaks")]
+pub async fn peaks(
+    format: ResponseFormat,
+    params: web::Query<PeaksQueryParams>,
+    storage: web::Data<SharedStorage>,
+) -> Result<HttpResponse, InsightsEndpointError> {
+    let wave_options = params.wave_options()?;
+
+    ingest_into_storage(&storage)
+        .await
+        .map_err(InsightsEndpointError::UnexpectedError)?;
+    let daily_cases = storage
+        .query(|storage| storage.daily_cases(None, None))
+        .await
+        .map_err(InsightsEndpointError::UnexpectedError)?;
+
+    let records = daily_cases
+        .records()
+        .ok_or_else(|| InsightsEndpointError::ResourceNotFound("There are no cases yet".into()))?;
+
+    let peaks = Peaks {
+        records,
+        waves: daily_cases.waves(wave_options),
+    };
+
+    format
+        .respond(&peaks)
+        .map_err(InsightsEndpointError::UnexpectedError)
+}
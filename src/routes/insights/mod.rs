@@ -0,0 +1,5 @@
+mod common;
+mod index;
+
+pub use common::*;
+pub use index::*;
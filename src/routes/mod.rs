@@ -1,5 +1,11 @@
+pub mod admin;
+pub mod aggregate;
+pub mod compare;
 pub mod daily;
 pub mod health;
 pub mod index;
+pub mod insights;
 pub mod monthly;
+pub mod quality;
+pub mod snapshots;
 pub mod yearly;
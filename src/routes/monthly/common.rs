@@ -4,13 +4,20 @@ pub mod middleware {
     use actix_web::{
         body::MessageBody,
         dev::{ServiceRequest, ServiceResponse},
+        error::ErrorBadRequest,
         HttpMessage,
     };
     use actix_web_lab::middleware::Next;
+    use chrono::NaiveDate;
 
-    use crate::types::QueryParams;
+    use crate::{
+        date_range::{DateBound, DateRange, Period},
+        fields::Fields,
+        pagination::Pagination,
+        types::QueryParams,
+    };
 
-    use super::types::{MonthlyQueryParams, YearMonth};
+    use super::types::MonthlyQueryParams;
 
     pub async fn filter_malformed_query_params(
         req: ServiceRequest,
@@ -19,57 +26,63 @@ pub mod middleware {
         let query_string = req.query_string();
         let query_params = serde_urlencoded::from_str::<QueryParams>(query_string)?;
 
-        let mut monthly_query_params = MonthlyQueryParams {
-            since: None,
-            upto: None,
+        let range = DateRange {
+            since: parse_month_bound(query_params.since.as_deref()).map_err(ErrorBadRequest)?,
+            upto: parse_month_bound(query_params.upto.as_deref()).map_err(ErrorBadRequest)?,
+            last: query_params
+                .last
+                .as_deref()
+                .map(Period::parse)
+                .transpose()
+                .map_err(ErrorBadRequest)?,
+        };
+
+        let monthly_query_params = MonthlyQueryParams {
+            range,
+            pagination: Pagination::from_query_params(&query_params).map_err(ErrorBadRequest)?,
+            fields: Fields::from_query_param(query_params.fields.as_deref())
+                .map_err(ErrorBadRequest)?,
+        };
+
+        req.extensions_mut().insert(monthly_query_params);
+        next.call(req).await
+    }
+
+    /// Parse either a relative date (`today-N`) or a month in ISO 8601 format (YYYY-MM),
+    /// the latter is represented by the first day of the month.<br>
+    /// Malformed ISO 8601 months are ignored, malformed relative dates are rejected.
+    fn parse_month_bound(value: Option<&str>) -> Result<Option<DateBound>, String> {
+        let value = match value {
+            Some(value) => value,
+            None => return Ok(None),
         };
 
-        if let Some(since) = query_params.since {
-            let splitted_params = since.split('-').collect::<Vec<_>>();
-
-            if splitted_params.len() == 2 {
-                let splitted_params = splitted_params
-                    .iter()
-                    .map(|x| x.parse::<i32>())
-                    .collect::<Result<Vec<i32>, ParseIntError>>();
-
-                if let Ok(params) = splitted_params {
-                    let (year, month) = (params[0], params[1]);
-                    monthly_query_params.since = Some(YearMonth {
-                        year,
-                        month: month as u32,
-                    });
-                }
-            }
+        if let Some(relative) = DateBound::parse_relative(value) {
+            return relative.map(Some);
         }
 
-        if let Some(upto) = query_params.upto {
-            let splitted_params = upto.split('-').collect::<Vec<_>>();
-
-            if splitted_params.len() == 2 {
-                let splitted_params = splitted_params
-                    .iter()
-                    .map(|x| x.parse::<i32>())
-                    .collect::<Result<Vec<i32>, ParseIntError>>();
-
-                if let Ok(params) = splitted_params {
-                    let (year, month) = (params[0], params[1]);
-                    monthly_query_params.upto = Some(YearMonth {
-                        year,
-                        month: month as u32,
-                    });
-                }
-            }
+        let splitted_params = value.split('-').collect::<Vec<_>>();
+        if splitted_params.len() != 2 {
+            return Ok(None);
         }
 
-        req.extensions_mut().insert(monthly_query_params);
-        next.call(req).await
+        let splitted_params = splitted_params
+            .iter()
+            .map(|x| x.parse::<i32>())
+            .collect::<Result<Vec<i32>, ParseIntError>>();
+
+        Ok(splitted_params.ok().and_then(|params| {
+            let (year, month) = (params[0], params[1]);
+            NaiveDate::from_ymd_opt(year, month as u32, 1).map(DateBound::Absolute)
+        }))
     }
 }
 
 pub mod types {
     use actix_web::{HttpResponse, ResponseError};
 
+    use crate::{date_range::DateRange, fields::Fields, pagination::Pagination};
+
     #[derive(Debug, derive_more::Display)]
     pub enum MonthlyEndpointError {
         #[display(fmt = "{}", _0)]
@@ -97,13 +110,8 @@ pub mod types {
 
     #[derive(Debug, Clone)]
     pub struct MonthlyQueryParams {
-        pub since: Option<YearMonth>,
-        pub upto: Option<YearMonth>,
-    }
-
-    #[derive(Debug, Clone)]
-    pub struct YearMonth {
-        pub year: i32,
-        pub month: u32,
+        pub range: DateRange,
+        pub pagination: Pagination,
+        pub fields: Fields,
     }
 }
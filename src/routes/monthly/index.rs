@@ -1,9 +1,7 @@
 use super::types::{MonthlyEndpointError, MonthlyQueryParams};
-use crate::utils::fetch_data_from_source_api;
+use crate::{response::ResponseFormat, storage::SharedStorage, utils::ingest_into_storage};
 
-use actix_web::{get, web, HttpResponse};
-use chrono::NaiveDate;
-use chrono_utilities::naive::DateTransitions;
+use actix_web::{get, web, HttpRequest, HttpResponse};
 
 /// Get all monthly cases.
 #[utoipa::path(
@@ -13,15 +11,45 @@ use chrono_utilities::naive::DateTransitions;
         (
             "since" = Option<String>,
             query,
-            description = "In ISO 8601 format but take the year and month only (YYYY-MM).",
+            description = "In ISO 8601 format but take the year and month only (YYYY-MM), or relative to the latest date in the data (today-N).",
             example = "2021-03"
         ),
         (
             "upto" = Option<String>,
             query,
-            description = "In ISO 8601 format but take the year and month only (YYYY-MM).",
+            description = "In ISO 8601 format but take the year and month only (YYYY-MM), or relative to the latest date in the data (today-N).",
             example = "2022-07"
-        )
+        ),
+        (
+            "last" = Option<String>,
+            query,
+            description = "Only the given period up to the latest date in the data, in days (30d), weeks (12w), months (6m), or years (1y). Months and years count the latest month as the first one.",
+            example = "6m"
+        ),
+        (
+            "limit" = Option<usize>,
+            query,
+            description = "Maximum number of items to return.",
+            example = 30
+        ),
+        (
+            "offset" = Option<usize>,
+            query,
+            description = "Number of items to skip.",
+            example = 0
+        ),
+        (
+            "order" = Option<String>,
+            query,
+            description = "Order of the items by date, either `asc` (default) or `desc`.",
+            example = "desc"
+        ),
+        (
+            "fields" = Option<String>,
+            query,
+            description = "Comma separated metrics to return, any of `positive`, `recovered`, `deaths`, and `active`. Defaults to all metrics.",
+            example = "positive,deaths"
+        ),
     ),
     responses(
         (status = 200, description = "Success getting the data.", body = [MonthlyCase]),
@@ -30,51 +58,31 @@ use chrono_utilities::naive::DateTransitions;
 )]
 #[get("")]
 pub async fn all_months(
+    req: HttpRequest,
+    format: ResponseFormat,
     params: web::ReqData<MonthlyQueryParams>,
+    storage: web::Data<SharedStorage>,
 ) -> Result<HttpResponse, MonthlyEndpointError> {
     let params = params.into_inner();
-    let mut daily_cases = fetch_data_from_source_api()
+    ingest_into_storage(&storage)
         .await
-        .map_err(MonthlyEndpointError::UnexpectedError)?
-        .to_daily();
-
-    if let Some(since) = params.since {
-        daily_cases = daily_cases
-            .0
-            .into_iter()
-            .filter(|daily| {
-                let daily_date = NaiveDate::from_ymd(daily.year, daily.month, daily.day);
-                let since_date = NaiveDate::from_ymd(since.year, since.month, 1);
-
-                let num_of_days_after_since =
-                    daily_date.signed_duration_since(since_date).num_days();
-
-                num_of_days_after_since >= 0
-            })
-            .collect();
-    }
-
-    if let Some(upto) = params.upto {
-        daily_cases = daily_cases
-            .0
-            .into_iter()
-            .filter(|daily| {
-                let current_daily_date = NaiveDate::from_ymd(daily.year, daily.month, daily.day);
-                let upto_date = NaiveDate::from_ymd(upto.year, upto.month, 1);
-                // Because upto_date is used to filter daily cases,
-                // thus we need to create a NaiveDate object that correctly points
-                // to the last day of the month in order to get all daily cases in a given month.
-                let upto_date =
-                    NaiveDate::from_ymd(upto.year, upto.month, upto_date.last_day_of_month());
-
-                let num_of_days_till_upto = current_daily_date
-                    .signed_duration_since(upto_date)
-                    .num_days();
+        .map_err(MonthlyEndpointError::UnexpectedError)?;
+    let range = params.range.clone();
+    let daily_cases = storage
+        .query(move |storage| {
+            let (since, upto) = range.resolve_months(storage.latest_date()?);
+            storage.daily_cases(since, upto)
+        })
+        .await
+        .map_err(MonthlyEndpointError::UnexpectedError)?;
 
-                num_of_days_till_upto <= 0
-            })
-            .collect();
-    }
+    let page = params.pagination.paginate(daily_cases.to_monthly().0);
+    let body = params
+        .fields
+        .select(&page.items)
+        .map_err(MonthlyEndpointError::UnexpectedError)?;
 
-    Ok(HttpResponse::Ok().json(daily_cases.to_monthly().0))
+    format
+        .respond_with(params.pagination.response_builder(&req, page.total), &body)
+        .map_err(MonthlyEndpointError::UnexpectedError)
 }
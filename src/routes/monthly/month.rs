@@ -1,7 +1,11 @@
-use super::types::MonthlyEndpointError;
-use crate::utils::fetch_data_from_source_api;
+use super::types::{MonthlyEndpointError, MonthlyQueryParams};
+use crate::{
+    response::ResponseFormat, storage::SharedStorage, types::DailyCases, utils::ingest_into_storage,
+};
 
 use actix_web::{get, web, HttpResponse};
+use chrono::NaiveDate;
+use chrono_utilities::naive::DateTransitions;
 
 /// Get a specific month's case.
 #[utoipa::path(
@@ -9,7 +13,13 @@ use actix_web::{get, web, HttpResponse};
     tag = "Data",
     params(
         ("year", description = "Selected year.", example = 2021),
-        ("month", description = "Selected month.", example = 7)
+        ("month", description = "Selected month.", example = 7),
+        (
+            "fields" = Option<String>,
+            query,
+            description = "Comma separated metrics to return, any of `positive`, `recovered`, `deaths`, and `active`. Defaults to all metrics.",
+            example = "positive,deaths"
+        ),
     ),
     responses(
         (status = 200, description = "Success getting the data.", body = MonthlyCase),
@@ -19,18 +29,35 @@ use actix_web::{get, web, HttpResponse};
 )]
 #[get("/{year}/{month}")]
 pub async fn specific_month(
+    format: ResponseFormat,
+    params: web::ReqData<MonthlyQueryParams>,
     path: web::Path<(i32, i32)>,
+    storage: web::Data<SharedStorage>,
 ) -> Result<HttpResponse, MonthlyEndpointError> {
     let (selected_year, selected_month) = path.into_inner();
 
-    let daily_cases = fetch_data_from_source_api()
+    ingest_into_storage(&storage)
         .await
-        .map_err(MonthlyEndpointError::UnexpectedError)?
-        .to_daily();
+        .map_err(MonthlyEndpointError::UnexpectedError)?;
+    let first_day = NaiveDate::from_ymd_opt(selected_year, selected_month as u32, 1);
+    let daily_cases = match first_day {
+        Some(first_day) => storage
+            .query(move |storage| storage.daily_cases(Some(first_day), first_day.end_of_month()))
+            .await
+            .map_err(MonthlyEndpointError::UnexpectedError)?,
+        None => DailyCases(Vec::new()),
+    };
 
-    Ok(HttpResponse::Ok().json(
-        daily_cases
-            .get_specific_month(selected_year, selected_month)
-            .map_err(MonthlyEndpointError::NotFound)?,
-    ))
+    let monthly_case = daily_cases
+        .get_specific_month(selected_year, selected_month)
+        .map_err(MonthlyEndpointError::NotFound)?;
+
+    let body = params
+        .fields
+        .select(&monthly_case)
+        .map_err(MonthlyEndpointError::UnexpectedError)?;
+
+    format
+        .respond(&body)
+        .map_err(MonthlyEndpointError::UnexpectedError)
 }
@@ -0,0 +1,58 @@
+use crate::{
+    response::ResponseFormat, snapshots::SnapshotStore, storage::SharedStorage,
+    utils::ingest_into_storage,
+};
+use actix_web::{web, HttpResponse, ResponseError};
+
+#[derive(Debug, derive_more::Display)]
+pub enum QualityEndpointError {
+    #[display(fmt = "{}", _0)]
+    UnexpectedError(String),
+}
+
+impl From<reqwest::Error> for QualityEndpointError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::UnexpectedError(err.to_string())
+    }
+}
+
+impl ResponseError for QualityEndpointError {
+    fn error_response(&self) -> HttpResponse {
+        let mut http_response = HttpResponse::InternalServerError();
+        http_response.body(self.to_string())
+    }
+}
+
+/// Get the data-quality report of all daily cases.
+///
+/// Flags gaps (missing days), negative values, statistical outliers such as batch-reporting
+/// spikes, and the past days revised by the source API's latest update.
+#[utoipa::path(
+    get,
+    path = "/quality",
+    tag = "Data",
+    responses(
+        (status = 200, description = "Success processing the data-quality report.", body = QualityReport),
+        (status = 500, description = "Something went wrong during the processing.", body = String),
+    )
+)]
+pub async fn quality_report(
+    format: ResponseFormat,
+    storage: web::Data<SharedStorage>,
+) -> Result<HttpResponse, QualityEndpointError> {
+    ingest_into_storage(&storage)
+        .await
+        .map_err(QualityEndpointError::UnexpectedError)?;
+    let daily_cases = storage
+        .query(|storage| storage.daily_cases(None, None))
+        .await
+        .map_err(QualityEndpointError::UnexpectedError)?;
+    let revisions = web::block(|| SnapshotStore::from_config().latest_revisions(None))
+        .await
+        .map_err(|err| QualityEndpointError::UnexpectedError(err.to_string()))?
+        .map_err(QualityEndpointError::UnexpectedError)?;
+
+    format
+        .respond(&daily_cases.quality_report(&revisions))
+        .map_err(QualityEndpointError::UnexpectedError)
+}
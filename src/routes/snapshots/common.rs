@@ -0,0 +1,37 @@
+pub mod errors {
+    use actix_web::{HttpResponse, ResponseError};
+    use std::fmt::Debug;
+
+    #[derive(Debug, derive_more::Display)]
+    pub enum SnapshotsEndpointError {
+        #[display(fmt = "{}", _0)]
+        UnexpectedError(String),
+        #[display(fmt = "{}", _0)]
+        ResourceNotFound(String),
+    }
+
+    impl ResponseError for SnapshotsEndpointError {
+        fn error_response(&self) -> HttpResponse {
+            let mut http_response = match self {
+                SnapshotsEndpointError::ResourceNotFound(_) => HttpResponse::NotFound(),
+                _ => HttpResponse::InternalServerError(),
+            };
+
+            http_response.body(self.to_string())
+        }
+    }
+}
+
+pub mod types {
+    use utoipa::IntoParams;
+
+    #[derive(serde::Deserialize, Debug, IntoParams)]
+    pub struct DiffQueryParams {
+        /// Id of the earlier snapshot.
+        #[param(example = 1661871600000_i64)]
+        pub from: i64,
+        /// Id of the later snapshot, defaults to the latest snapshot.
+        #[param(example = 1661958000000_i64)]
+        pub to: Option<i64>,
+    }
+}
@@ -0,0 +1,83 @@
+use super::{common::types::DiffQueryParams, errors::SnapshotsEndpointError};
+use crate::{
+    response::ResponseFormat,
+    snapshots::SnapshotStore,
+    types::{Snapshot, SnapshotDiff},
+};
+
+use actix_web::{get, web, HttpResponse};
+use utoipa::IntoParams;
+
+/// Compare two snapshots to see which past daily cases were revised.
+#[utoipa::path(
+    context_path = "/snapshots",
+    tag = "Data",
+    responses(
+        (status = 200, description = "Success comparing the snapshots.", body = SnapshotDiff),
+        (status = 404, description = "There is no snapshot with one of the given ids.", body = String),
+        (status = 500, description = "Something went wrong during the processing.", body = String),
+    )
+)]
+#[get("/diff")]
+pub async fn diff_snapshots(
+    format: ResponseFormat,
+    params: web::Query<DiffQueryParams>,
+) -> Result<HttpResponse, SnapshotsEndpointError> {
+    let (from, to) = (params.from, params.to);
+    // Reading and parsing the snapshots blocks.
+    let diff = web::block(move || diff(&SnapshotStore::from_config(), from, to))
+        .await
+        .map_err(|err| SnapshotsEndpointError::UnexpectedError(err.to_string()))??;
+
+    format
+        .respond(&diff)
+        .map_err(SnapshotsEndpointError::UnexpectedError)
+}
+
+/// Diff of the snapshot `from` to the snapshot `to`, or to the latest one.
+fn diff(
+    store: &SnapshotStore,
+    from: i64,
+    to: Option<i64>,
+) -> Result<SnapshotDiff, SnapshotsEndpointError> {
+    // Ids out of a timestamp's range can't be the ones of a snapshot.
+    let snapshot_of = |id| {
+        Snapshot::new(id).ok_or_else(|| {
+            SnapshotsEndpointError::ResourceNotFound(format!("There is no snapshot {id}"))
+        })
+    };
+
+    let to = match to {
+        Some(id) => snapshot_of(id)?,
+        None => store
+            .latest()
+            .map_err(SnapshotsEndpointError::UnexpectedError)?
+            .ok_or_else(|| {
+                SnapshotsEndpointError::ResourceNotFound("There are no snapshots yet".into())
+            })?,
+    };
+    let from = snapshot_of(from)?;
+
+    let load = |snapshot: &Snapshot| {
+        store
+            .load(snapshot.id)
+            .map_err(SnapshotsEndpointError::UnexpectedError)?
+            .map(|source| source.to_daily())
+            .ok_or_else(|| {
+                SnapshotsEndpointError::ResourceNotFound(format!(
+                    "There is no snapshot {}",
+                    snapshot.id
+                ))
+            })
+    };
+
+    let (revisions, added_days, removed_days) = load(&to)?.revisions_since(&load(&from)?);
+
+    Ok(SnapshotDiff {
+        from,
+        to,
+        added_days,
+        removed_days,
+        revisions,
+    })
+}
@@ -0,0 +1,26 @@
+use super::errors::SnapshotsEndpointError;
+use crate::{response::ResponseFormat, snapshots::SnapshotStore};
+
+use actix_web::{get, HttpResponse};
+
+/// List the stored snapshots of the source data, from the oldest to the latest.
+///
+/// A snapshot is stored whenever the source data changed since the latest one.
+#[utoipa::path(
+    context_path = "/snapshots",
+    tag = "Data",
+    responses(
+        (status = 200, description = "Success getting the snapshots.", body = [Snapshot]),
+        (status = 500, description = "Something went wrong during the processing.", body = String),
+    )
+)]
+#[get("")]
+pub async fn all_snapshots(format: ResponseFormat) -> Result<HttpResponse, SnapshotsEndpointError> {
+    let snapshots = SnapshotStore::from_config()
+        .list()
+        .map_err(SnapshotsEndpointError::UnexpectedError)?;
+
+    format
+        .respond(&snapshots)
+        .map_err(SnapshotsEndpointError::UnexpectedError)
+}
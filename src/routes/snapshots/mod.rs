@@ -0,0 +1,7 @@
+mod common;
+mod diff;
+mod index;
+
+pub use common::*;
+pub use diff::*;
+pub use index::*;
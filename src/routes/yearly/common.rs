@@ -42,5 +42,17 @@ pub mod types {
         /// The upper boundary of the yearly cases.
         #[param(example = 2021)]
         pub upto: Option<i32>,
+        /// Comma separated metrics to return, any of `positive`, `recovered`, `deaths`, and `active`.
+        /// Defaults to all metrics.
+        #[param(example = "positive,deaths")]
+        pub fields: Option<String>,
+    }
+
+    #[derive(serde::Deserialize, Debug, IntoParams)]
+    pub struct SpecificYearQueryParams {
+        /// Comma separated metrics to return, any of `positive`, `recovered`, `deaths`, and `active`.
+        /// Defaults to all metrics.
+        #[param(example = "positive,deaths")]
+        pub fields: Option<String>,
     }
 }
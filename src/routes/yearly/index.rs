@@ -1,6 +1,12 @@
 use super::{common::types::QueryParams, errors::YearlyEndpointError};
-use crate::utils::fetch_data_from_source_api;
+use crate::{
+    fields::Fields, response::ResponseFormat, storage::SharedStorage, utils::ingest_into_storage,
+};
 use actix_web::{get, web, HttpResponse};
+use chrono::{
+    naive::{MAX_DATE, MIN_DATE},
+    Datelike, NaiveDate,
+};
 use utoipa::IntoParams;
 
 /// Get all yearly cases.
@@ -14,28 +20,34 @@ use utoipa::IntoParams;
 )]
 #[get("")]
 pub async fn all_years(
+    format: ResponseFormat,
     params: web::Query<QueryParams>,
+    storage: web::Data<SharedStorage>,
 ) -> Result<HttpResponse, YearlyEndpointError> {
-    let mut daily_cases = fetch_data_from_source_api()
+    let fields = Fields::from_query_param(params.fields.as_deref())
+        .map_err(YearlyEndpointError::BadRequest)?;
+
+    ingest_into_storage(&storage)
+        .await
+        .map_err(YearlyEndpointError::UnexpectedError)?;
+    let since = params.since.and_then(|since| date_of(since, 1, 1));
+    let upto = params.upto.and_then(|upto| date_of(upto, 12, 31));
+    let daily_cases = storage
+        .query(move |storage| storage.daily_cases(since, upto))
         .await
-        .map_err(YearlyEndpointError::UnexpectedError)?
-        .to_daily();
+        .map_err(YearlyEndpointError::UnexpectedError)?;
 
-    if let Some(since) = params.since {
-        daily_cases = daily_cases
-            .0
-            .into_iter()
-            .filter(|daily| daily.year >= since)
-            .collect();
-    }
+    let body = fields
+        .select(&daily_cases.to_yearly().0)
+        .map_err(YearlyEndpointError::UnexpectedError)?;
 
-    if let Some(upto) = params.upto {
-        daily_cases = daily_cases
-            .0
-            .into_iter()
-            .filter(|daily| daily.year <= upto)
-            .collect();
-    }
+    format
+        .respond(&body)
+        .map_err(YearlyEndpointError::UnexpectedError)
+}
 
-    Ok(HttpResponse::Ok().json(daily_cases.to_yearly().0))
+/// A date of `year`, clamped to the supported years so that a year beyond them still bounds the
+/// range, e.g. `since=999999` leaves no year rather than every one.
+fn date_of(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(year.clamp(MIN_DATE.year(), MAX_DATE.year()), month, day)
 }
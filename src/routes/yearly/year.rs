@@ -1,7 +1,11 @@
-use super::errors::YearlyEndpointError;
-use crate::utils::fetch_data_from_source_api;
+use super::{common::types::SpecificYearQueryParams, errors::YearlyEndpointError};
+use crate::{
+    fields::Fields, response::ResponseFormat, storage::SharedStorage, utils::ingest_into_storage,
+};
 
 use actix_web::{get, web, HttpResponse};
+use chrono::NaiveDate;
+use utoipa::IntoParams;
 
 /// Get a specific year's case.
 #[utoipa::path(
@@ -16,18 +20,34 @@ use actix_web::{get, web, HttpResponse};
 )]
 #[get("/{year}")]
 pub async fn specific_year(
+    format: ResponseFormat,
+    params: web::Query<SpecificYearQueryParams>,
     year: web::Path<i32>,
+    storage: web::Data<SharedStorage>,
 ) -> actix_web::Result<HttpResponse, YearlyEndpointError> {
+    let fields = Fields::from_query_param(params.fields.as_deref())
+        .map_err(YearlyEndpointError::BadRequest)?;
     let selected_year = year.into_inner();
 
-    let daily = fetch_data_from_source_api()
+    ingest_into_storage(&storage)
         .await
-        .map_err(YearlyEndpointError::UnexpectedError)?
-        .to_daily();
+        .map_err(YearlyEndpointError::UnexpectedError)?;
+    let since = NaiveDate::from_ymd_opt(selected_year, 1, 1);
+    let upto = NaiveDate::from_ymd_opt(selected_year, 12, 31);
+    let daily = storage
+        .query(move |storage| storage.daily_cases(since, upto))
+        .await
+        .map_err(YearlyEndpointError::UnexpectedError)?;
+
+    let yearly_case = daily
+        .to_specific_yearly(selected_year)
+        .map_err(YearlyEndpointError::ResourceNotFound)?;
+
+    let body = fields
+        .select(&yearly_case)
+        .map_err(YearlyEndpointError::UnexpectedError)?;
 
-    Ok(HttpResponse::Ok().json(
-        daily
-            .to_specific_yearly(selected_year)
-            .map_err(YearlyEndpointError::ResourceNotFound)?,
-    ))
+    format
+        .respond(&body)
+        .map_err(YearlyEndpointError::UnexpectedError)
 }
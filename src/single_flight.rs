@@ -0,0 +1,107 @@
+use std::{collections::BTreeMap, future::Future, sync::Mutex};
+
+use tokio::sync::oneshot;
+
+/// Coalesces concurrent computations of the same key, across the workers' threads: the first
+/// caller computes the value, the ones coming in meanwhile wait for it and share it.
+pub struct SingleFlight<K, V> {
+    waiters: Mutex<BTreeMap<K, Vec<oneshot::Sender<V>>>>,
+}
+
+/// Part a caller plays in the computation of a key.
+pub enum Flight<'a, K: Ord, V: Clone> {
+    /// The caller computes the value, and hands it to the followers with [`Leader::complete`].
+    Leader(Leader<'a, K, V>),
+    /// The caller waits for the leader's value, an error meaning that the leader gave up.
+    Follower(oneshot::Receiver<V>),
+}
+
+pub struct Leader<'a, K: Ord, V: Clone> {
+    flight: &'a SingleFlight<K, V>,
+    key: Option<K>,
+}
+
+impl<K: Ord + Clone, V: Clone> SingleFlight<K, V> {
+    pub const fn new() -> Self {
+        Self {
+            waiters: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn join(&self, key: K) -> Flight<'_, K, V> {
+        let mut waiters = self.waiters.lock().unwrap_or_else(|err| err.into_inner());
+
+        match waiters.get_mut(&key) {
+            Some(followers) => {
+                let (sender, receiver) = oneshot::channel();
+                followers.push(sender);
+                Flight::Follower(receiver)
+            }
+            None => {
+                waiters.insert(key.clone(), Vec::new());
+                Flight::Leader(Leader {
+                    flight: self,
+                    key: Some(key),
+                })
+            }
+        }
+    }
+
+    /// Compute the value of `key` with `compute`, unless it's already being computed.<br>
+    /// Should the leader give up, e.g. because its request was cancelled, the value is computed
+    /// again rather than shared.
+    pub async fn run<F, Fut>(&self, key: K, compute: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        match self.join(key) {
+            Flight::Leader(leader) => {
+                let value = compute().await;
+                leader.complete(value.clone());
+                value
+            }
+            Flight::Follower(receiver) => match receiver.await {
+                Ok(value) => value,
+                Err(_) => compute().await,
+            },
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Default for SingleFlight<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V: Clone> Leader<'_, K, V> {
+    /// Hand `value` to the followers, the next caller computes the value again.
+    pub fn complete(mut self, value: V) {
+        for follower in self.take_followers() {
+            // A follower that's gone doesn't need the value anymore.
+            let _ = follower.send(value.clone());
+        }
+    }
+
+    fn take_followers(&mut self) -> Vec<oneshot::Sender<V>> {
+        let key = match self.key.take() {
+            Some(key) => key,
+            None => return Vec::new(),
+        };
+
+        self.flight
+            .waiters
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(&key)
+            .unwrap_or_default()
+    }
+}
+
+impl<K: Ord, V: Clone> Drop for Leader<'_, K, V> {
+    /// Dropping the followers' senders lets them know that no value is coming.
+    fn drop(&mut self) {
+        self.take_followers();
+    }
+}
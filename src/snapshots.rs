@@ -0,0 +1,224 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+use crate::{
+    config,
+    fields::METRICS,
+    storage::{SharedStorage, Storage},
+    types::{source_api::SourceAPIResponse, DailyCase, DailyCases, DailyRevision, Snapshot},
+};
+
+/// Directory the snapshots are stored in unless configured otherwise.
+pub const DEFAULT_SNAPSHOTS_DIR: &str = "snapshots";
+
+/// Number of snapshots whose daily cases are kept in memory, from the most recently queried.
+const MAX_SNAPSHOT_STORAGES: usize = 8;
+
+/// In-memory storages of the recently queried snapshots, from the least recently queried, keyed
+/// by the snapshots' directory and id.<br>
+/// A snapshot never changes once stored, so neither does its storage.
+static SNAPSHOT_STORAGES: Mutex<Vec<((PathBuf, i64), SharedStorage)>> = Mutex::new(Vec::new());
+
+/// Every distinct response of the source API, stored as `{id}.json` files in a directory.
+///
+/// A snapshot's id is the time it was fetched at, in milliseconds since the Unix epoch.
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Store in the configured `storage.snapshots_dir`.
+    pub fn from_config() -> Self {
+        Self::new(&config::get().storage.snapshots_dir)
+    }
+
+    /// Every stored snapshot, ordered from the oldest to the latest.
+    pub fn list(&self) -> Result<Vec<Snapshot>, String> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.to_string()),
+        };
+
+        let mut ids = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                match path.extension()?.to_str()? {
+                    "json" => path.file_stem()?.to_str()?.parse::<i64>().ok(),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        ids.sort_unstable();
+
+        Ok(ids.into_iter().filter_map(Snapshot::new).collect())
+    }
+
+    pub fn latest(&self) -> Result<Option<Snapshot>, String> {
+        Ok(self.list()?.pop())
+    }
+
+    /// Load the source API's response of a snapshot, `None` if there's no such snapshot.
+    pub fn load(&self, id: i64) -> Result<Option<SourceAPIResponse>, String> {
+        let body = match fs::read(self.path_of(id)) {
+            Ok(body) => body,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.to_string()),
+        };
+
+        serde_json::from_slice(&body)
+            .map(Some)
+            .map_err(|err| err.to_string())
+    }
+
+    /// In-memory storage holding the daily cases of a snapshot, `None` if there's no such snapshot.
+    ///
+    /// Loading a snapshot reads and parses the whole response, so this blocks, and the storages
+    /// of the recently queried snapshots are reused.
+    pub fn storage(&self, id: i64) -> Result<Option<SharedStorage>, String> {
+        let key = (self.dir.clone(), id);
+        {
+            let mut storages = SNAPSHOT_STORAGES.lock().map_err(|err| err.to_string())?;
+            if let Some(index) = storages.iter().position(|(cached, _)| *cached == key) {
+                let entry = storages.remove(index);
+                let storage = entry.1.clone();
+                storages.push(entry);
+                return Ok(Some(storage));
+            }
+        }
+
+        let daily_cases = match self.load(id)? {
+            Some(source) => source.to_daily(),
+            None => return Ok(None),
+        };
+        let mut storage = Storage::open_in_memory()?;
+        storage.upsert_daily_cases(&daily_cases)?;
+        let storage = SharedStorage::new(storage);
+
+        let mut storages = SNAPSHOT_STORAGES.lock().map_err(|err| err.to_string())?;
+        if storages.len() >= MAX_SNAPSHOT_STORAGES {
+            storages.remove(0);
+        }
+        storages.push((key, storage.clone()));
+
+        Ok(Some(storage))
+    }
+
+    /// Revisions that the latest snapshot, or the latest one up to `as_of`, made to the one
+    /// before it.
+    pub fn latest_revisions(&self, as_of: Option<i64>) -> Result<Vec<DailyRevision>, String> {
+        let snapshots = self
+            .list()?
+            .into_iter()
+            .filter(|snapshot| as_of.is_none_or(|as_of| snapshot.id <= as_of))
+            .collect::<Vec<_>>();
+        let (earlier, later) = match snapshots.as_slice() {
+            [.., earlier, later] => (earlier.id, later.id),
+            _ => return Ok(Vec::new()),
+        };
+
+        let daily_cases_of = |id| {
+            self.load(id)
+                .map(|source| source.map(|source| source.to_daily()))
+        };
+        match (daily_cases_of(earlier)?, daily_cases_of(later)?) {
+            (Some(earlier), Some(later)) => Ok(later.revisions_since(&earlier).0),
+            // Deleted since they were listed.
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Store the raw body of a response fetched at `fetched_at`.<br>
+    /// Nothing is stored if the body is identical to the latest snapshot, which is returned instead.
+    pub fn save(&self, body: &[u8], fetched_at: DateTime<Utc>) -> Result<Snapshot, String> {
+        if let Some(latest) = self.latest()? {
+            if fs::read(self.path_of(latest.id)).is_ok_and(|latest_body| latest_body == body) {
+                return Ok(latest);
+            }
+        }
+
+        fs::create_dir_all(&self.dir).map_err(|err| err.to_string())?;
+
+        // Written under a temporary name first, so that a snapshot is never listed half-written.
+        let snapshot = Snapshot::new(fetched_at.timestamp_millis())
+            .ok_or_else(|| format!("{fetched_at} is out of range for a snapshot"))?;
+        let temporary_path = self.path_of(snapshot.id).with_extension("json.tmp");
+        fs::write(&temporary_path, body).map_err(|err| err.to_string())?;
+        fs::rename(&temporary_path, self.path_of(snapshot.id)).map_err(|err| err.to_string())?;
+
+        Ok(snapshot)
+    }
+
+    fn path_of(&self, id: i64) -> PathBuf {
+        Path::new(&self.dir).join(format!("{id}.json"))
+    }
+}
+
+impl Snapshot {
+    /// The snapshot fetched at `id` milliseconds since the epoch, `None` when it's out of range.
+    pub fn new(id: i64) -> Option<Self> {
+        let fetched_at = Utc.timestamp_millis_opt(id).single()?;
+
+        Some(Self {
+            id,
+            fetched_at: fetched_at.to_rfc3339(),
+        })
+    }
+}
+
+impl DailyCases {
+    /// Compare the daily cases with the ones of an earlier snapshot.
+    ///
+    /// Returns every metric that changed on a day present in both, along with the number of days
+    /// only present in this one (added) and the number of days only present in the earlier one (removed).
+    pub fn revisions_since(&self, earlier: &DailyCases) -> (Vec<DailyRevision>, u32, u32) {
+        let (before, after) = (by_date(earlier), by_date(self));
+
+        let removed_days = before
+            .keys()
+            .filter(|date| !after.contains_key(date))
+            .count();
+        let added_days = after
+            .keys()
+            .filter(|date| !before.contains_key(date))
+            .count();
+
+        let revisions = after
+            .iter()
+            .filter_map(|(date, after)| Some((date, before.get(date)?, after)))
+            .flat_map(|(date, before, after)| {
+                METRICS
+                    .iter()
+                    .zip(before.metrics().into_iter().zip(after.metrics()))
+                    .filter(|(_, (before, after))| before != after)
+                    .map(|(metric, (before, after))| DailyRevision {
+                        date: date.to_string(),
+                        metric: metric.to_string(),
+                        before,
+                        after,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        (revisions, added_days as u32, removed_days as u32)
+    }
+}
+
+fn by_date(daily_cases: &DailyCases) -> BTreeMap<NaiveDate, &DailyCase> {
+    daily_cases
+        .0
+        .iter()
+        .map(|daily| (daily.date(), daily))
+        .collect()
+}
@@ -0,0 +1,222 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{Datelike, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{
+    config,
+    types::{DailyCase, DailyCases},
+};
+
+/// Database file used unless configured otherwise.
+pub const DEFAULT_DATABASE_PATH: &str = "covid.db";
+
+/// Schema migrations, applied in order.<br>
+/// The number of applied migrations is kept in SQLite's `user_version`, so a migration must never
+/// be changed or removed once released, only new ones appended.
+const MIGRATIONS: &[&str] = &[include_str!("../migrations/0001_create_daily_cases.sql")];
+
+/// SQLite database holding the normalized history of daily cases.
+pub struct Storage {
+    connection: Connection,
+}
+
+impl Storage {
+    /// Open the database at `path`, creating it if needed, and apply the pending migrations.
+    pub fn open(path: &str) -> Result<Self, String> {
+        let connection = Connection::open(path).map_err(|err| err.to_string())?;
+        Self::from_connection(connection)
+    }
+
+    /// Open the database at the configured `storage.database_path`.
+    pub fn open_from_config() -> Result<Self, String> {
+        Self::open(&config::get().storage.database_path)
+    }
+
+    /// Open a private database that only lives as long as the returned storage.
+    pub fn open_in_memory() -> Result<Self, String> {
+        let connection = Connection::open_in_memory().map_err(|err| err.to_string())?;
+        Self::from_connection(connection)
+    }
+
+    fn from_connection(mut connection: Connection) -> Result<Self, String> {
+        // Concurrent requests upsert into the same file, wait for each other instead of failing.
+        connection
+            .busy_timeout(Duration::from_secs(5))
+            .map_err(|err| err.to_string())?;
+
+        migrate(&mut connection).map_err(|err| err.to_string())?;
+
+        Ok(Self { connection })
+    }
+
+    /// Insert the daily cases, or update the stored ones of the same dates.<br>
+    /// Returns the number of days that were inserted or whose values changed.
+    pub fn upsert_daily_cases(&mut self, daily_cases: &DailyCases) -> Result<usize, String> {
+        let updated_at = Utc::now().to_rfc3339();
+        let transaction = self
+            .connection
+            .transaction()
+            .map_err(|err| err.to_string())?;
+
+        let mut changed = 0;
+        {
+            let mut statement = transaction
+                .prepare_cached(
+                    "INSERT INTO daily_cases (date, positive, recovered, deaths, active, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                     ON CONFLICT (date) DO UPDATE SET
+                         positive = excluded.positive,
+                         recovered = excluded.recovered,
+                         deaths = excluded.deaths,
+                         active = excluded.active,
+                         updated_at = excluded.updated_at
+                     WHERE (positive, recovered, deaths, active)
+                         IS NOT (excluded.positive, excluded.recovered, excluded.deaths, excluded.active)",
+                )
+                .map_err(|err| err.to_string())?;
+
+            for daily in &daily_cases.0 {
+                changed += statement
+                    .execute(params![
+                        daily.date().to_string(),
+                        daily.positive,
+                        daily.recovered,
+                        daily.deaths,
+                        daily.active,
+                        updated_at,
+                    ])
+                    .map_err(|err| err.to_string())?;
+            }
+        }
+
+        transaction.commit().map_err(|err| err.to_string())?;
+        Ok(changed)
+    }
+
+    /// Stored daily cases between `since` and `upto` (both inclusive) ordered by date,
+    /// an omitted boundary is unbounded.
+    pub fn daily_cases(
+        &self,
+        since: Option<NaiveDate>,
+        upto: Option<NaiveDate>,
+    ) -> Result<DailyCases, String> {
+        // Dates are compared as text, which only orders them right within 4-digit years.
+        let (earliest, latest) = (
+            NaiveDate::from_ymd(0, 1, 1),
+            NaiveDate::from_ymd(9999, 12, 31),
+        );
+        if since.is_some_and(|since| since > latest) || upto.is_some_and(|upto| upto < earliest) {
+            return Ok(DailyCases(Vec::new()));
+        }
+        let since = since.filter(|since| *since > earliest);
+        let upto = upto.filter(|upto| *upto < latest);
+
+        let mut statement = self
+            .connection
+            .prepare_cached(
+                "SELECT date, positive, recovered, deaths, active FROM daily_cases
+                 WHERE (?1 IS NULL OR date >= ?1) AND (?2 IS NULL OR date <= ?2)
+                 ORDER BY date",
+            )
+            .map_err(|err| err.to_string())?;
+
+        let rows = statement
+            .query_map(
+                params![
+                    since.map(|since| since.to_string()),
+                    upto.map(|upto| upto.to_string())
+                ],
+                |row| {
+                    let date = row.get::<_, String>(0)?;
+                    Ok((date, [row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?]))
+                },
+            )
+            .map_err(|err| err.to_string())?;
+
+        rows.map(|row| {
+            let (date, [positive, recovered, deaths, active]) =
+                row.map_err(|err| err.to_string())?;
+            let date = parse_date(&date)?;
+
+            Ok(DailyCase {
+                year: date.year(),
+                month: date.month(),
+                day: date.day(),
+                positive,
+                recovered,
+                deaths,
+                active,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map(DailyCases)
+    }
+
+    /// Date of the most recent stored daily case.
+    pub fn latest_date(&self) -> Result<Option<NaiveDate>, String> {
+        self.connection
+            .query_row("SELECT MAX(date) FROM daily_cases", [], |row| {
+                row.get::<_, Option<String>>(0)
+            })
+            .optional()
+            .map_err(|err| err.to_string())?
+            .flatten()
+            .map(|date| parse_date(&date))
+            .transpose()
+    }
+}
+
+/// Storage shared by the workers, so that it's opened and migrated once at boot.
+///
+/// SQLite blocks, so the storage is only queried on the blocking thread pool, one query at a time.
+#[derive(Clone)]
+pub struct SharedStorage(Arc<Mutex<Storage>>);
+
+impl SharedStorage {
+    pub fn new(storage: Storage) -> Self {
+        Self(Arc::new(Mutex::new(storage)))
+    }
+
+    /// Open the database at the configured `storage.database_path`.
+    pub fn open_from_config() -> Result<Self, String> {
+        Storage::open_from_config().map(Self::new)
+    }
+
+    /// Run `query` on the storage, off the async workers.
+    pub async fn query<T, F>(&self, query: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Storage) -> Result<T, String> + Send + 'static,
+    {
+        let storage = self.0.clone();
+
+        actix_web::web::block(move || {
+            let mut storage = storage.lock().map_err(|err| err.to_string())?;
+            query(&mut storage)
+        })
+        .await
+        .map_err(|err| err.to_string())?
+    }
+}
+
+fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+    let applied =
+        connection.pragma_query_value(None, "user_version", |row| row.get::<_, usize>(0))?;
+
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", version + 1)?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|err| err.to_string())
+}
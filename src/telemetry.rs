@@ -0,0 +1,103 @@
+use std::io::{self, IsTerminal};
+
+use tracing::Subscriber;
+use tracing_subscriber::{
+    fmt::{self, MakeWriter},
+    layer::SubscriberExt,
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+};
+
+use crate::config::{LogFormat, LoggingConfig};
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Log to the standard error as configured, and export the traces if an OTLP endpoint is set.
+///
+/// `RUST_LOG` takes precedence over `logging.level`, e.g. `RUST_LOG=rust_covid_api=debug`.
+pub fn init(config: &LoggingConfig) -> Result<(), String> {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(config.level.as_str()));
+
+    let (text, json) = match config.format {
+        LogFormat::Text => (
+            Some(
+                fmt::layer()
+                    .with_ansi(io::stderr().is_terminal())
+                    .with_writer(io::stderr),
+            ),
+            None,
+        ),
+        LogFormat::Json => (None, Some(json_layer(io::stderr))),
+    };
+
+    tracing_subscriber::registry()
+        .with(otlp_layer(config)?)
+        .with(text)
+        .with(json)
+        .with(filter)
+        .try_init()
+        .map_err(|err| err.to_string())
+}
+
+/// Log as JSON lines to `writer`.
+///
+/// The fields of every entered span are listed under `spans`, so that the logs of nested spans,
+/// e.g. the upstream fetches of a request, still carry the `request_id` of their `http_request` span.
+pub fn json_layer<S, W>(writer: W) -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_writer(writer)
+}
+
+/// Flush the traces that haven't been exported yet.
+pub fn shutdown() {
+    #[cfg(feature = "otlp")]
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_layer(config: &LoggingConfig) -> Result<Option<BoxedLayer>, String> {
+    use opentelemetry::{sdk::trace, sdk::Resource, KeyValue};
+    use opentelemetry_otlp::WithExportConfig;
+
+    let endpoint = match &config.otlp_endpoint {
+        Some(endpoint) => endpoint,
+        None => return Ok(None),
+    };
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            trace::config().with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                env!("CARGO_PKG_NAME"),
+            )])),
+        )
+        .install_batch(opentelemetry::runtime::TokioCurrentThread)
+        .map_err(|err| err.to_string())?;
+
+    Ok(Some(
+        tracing_opentelemetry::layer().with_tracer(tracer).boxed(),
+    ))
+}
+
+#[cfg(not(feature = "otlp"))]
+fn otlp_layer(config: &LoggingConfig) -> Result<Option<BoxedLayer>, String> {
+    match config.otlp_endpoint {
+        Some(_) => Err("logging.otlp_endpoint needs a build with the `otlp` feature".to_string()),
+        None => Ok(None),
+    }
+}
@@ -1,13 +1,38 @@
-use std::collections::HashSet;
+use std::collections::BTreeMap;
 
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use utoipa::Component;
 
+use crate::{config::AuthScope, fill::Fill, pagination::SortOrder};
+
 pub struct DailyCases(pub Vec<DailyCase>);
 pub struct MonthlyCases(pub Vec<MonthlyCase>);
 pub struct YearlyCases(pub Vec<YearlyCase>);
 
 impl DailyCases {
+    /// Date of the earliest daily case.
+    pub fn earliest_date(&self) -> Option<NaiveDate> {
+        self.0.iter().map(DailyCase::date).min()
+    }
+
+    /// Date of the most recent daily case.
+    pub fn latest_date(&self) -> Option<NaiveDate> {
+        self.0.iter().map(DailyCase::date).max()
+    }
+
+    /// Keep the daily cases between `since` and `upto` (both inclusive), an omitted boundary is unbounded.
+    pub fn within(self, since: Option<NaiveDate>, upto: Option<NaiveDate>) -> DailyCases {
+        self.0
+            .into_iter()
+            .filter(|daily| {
+                let daily_date = daily.date();
+                since.is_none_or(|since| daily_date >= since)
+                    && upto.is_none_or(|upto| daily_date <= upto)
+            })
+            .collect()
+    }
+
     pub fn get_all_days_in_a_year(self, year: i32) -> Result<DailyCases, String> {
         let filtered = self
             .0
@@ -54,56 +79,43 @@ impl DailyCases {
         }
     }
 
-    /// Get distinct months from all daily cases in a year.<br>
-    /// **Output**: `[10, 11, 12]`
-    fn get_distinct_months(&self, year: &i32) -> Vec<u32> {
-        let mut distinct_months = self
-            .0
+    pub fn get_latest_day(self) -> Result<DailyCase, String> {
+        match self.0.into_iter().max_by_key(DailyCase::date) {
+            Some(daily_case) => Ok(daily_case),
+            None => Err("There are no daily cases yet".into()),
+        }
+    }
+
+    /// Sum up the daily cases that fall into the same bucket, ordered by bucket.<br>
+    /// This is the single fold behind every aggregated format, e.g. monthly buckets
+    /// are keyed by `(year, month)`.
+    pub fn fold_by<K: Ord>(&self, bucket_of: impl Fn(&DailyCase) -> K) -> Vec<(K, CaseTotals)> {
+        self.0
             .iter()
-            .filter(|daily_item| daily_item.year == *year)
-            .map(|daily_item| daily_item.month)
-            .collect::<HashSet<_>>()
+            .fold(BTreeMap::new(), |mut buckets, daily| {
+                buckets
+                    .entry(bucket_of(daily))
+                    .or_insert_with(CaseTotals::default)
+                    .add(daily);
+                buckets
+            })
             .into_iter()
-            .collect::<Vec<_>>();
-
-        distinct_months.sort_unstable();
-        distinct_months
+            .collect()
     }
 
+    #[tracing::instrument(skip_all, fields(days = self.0.len()))]
     pub fn to_monthly(&self) -> MonthlyCases {
-        let years_list = self.get_distinct_years();
-
-        let mut to_return: Vec<MonthlyCase> = Vec::new();
-        years_list.iter().for_each(|current_year| {
-            let months_list = self.get_distinct_months(current_year);
-            months_list.iter().for_each(|current_month| {
-                let folded = self
-                    .0
-                    .iter()
-                    .filter(|daily| daily.year == *current_year && daily.month == *current_month)
-                    .fold(
-                        MonthlyCase {
-                            year: *current_year,
-                            month: *current_month,
-                            positive: 0,
-                            recovered: 0,
-                            deaths: 0,
-                            active: 0,
-                        },
-                        |mut acc, next| {
-                            acc.positive += next.positive;
-                            acc.recovered += next.recovered;
-                            acc.deaths += next.deaths;
-                            acc.active += next.active;
-                            acc
-                        },
-                    );
-
-                to_return.push(folded);
+        self.fold_by(|daily| (daily.year, daily.month))
+            .into_iter()
+            .map(|((year, month), totals)| MonthlyCase {
+                year,
+                month,
+                positive: totals.positive,
+                recovered: totals.recovered,
+                deaths: totals.deaths,
+                active: totals.active,
             })
-        });
-
-        MonthlyCases(to_return)
+            .collect()
     }
 
     pub fn get_all_months_in_a_year(&self, year: i32) -> Result<MonthlyCases, String> {
@@ -133,21 +145,6 @@ impl DailyCases {
         }
     }
 
-    /// Get distinct years from all daily cases.<br>
-    /// **Output**: `[2019, 2020, 2021, 2022]`
-    fn get_distinct_years(&self) -> Vec<i32> {
-        let mut distinct_years = self
-            .0
-            .iter()
-            .map(|daily_item| daily_item.year)
-            .collect::<HashSet<_>>()
-            .into_iter()
-            .collect::<Vec<_>>();
-
-        distinct_years.sort_unstable();
-        distinct_years
-    }
-
     /// Convert daily into yearly format.
     ///
     /// #### Output
@@ -170,32 +167,18 @@ impl DailyCases {
     ///     ...
     /// ]
     /// ```
+    #[tracing::instrument(skip_all, fields(days = self.0.len()))]
     pub fn to_yearly(&self) -> YearlyCases {
-        let years_list = self.get_distinct_years();
-
-        let mut to_return: Vec<YearlyCase> = Vec::new();
-        years_list.iter().for_each(|year| {
-            let folded = self.0.iter().filter(|daily| daily.year == *year).fold(
-                YearlyCase {
-                    year: *year,
-                    positive: 0,
-                    recovered: 0,
-                    deaths: 0,
-                    active: 0,
-                },
-                |mut acc, next| {
-                    acc.positive += next.positive;
-                    acc.recovered += next.recovered;
-                    acc.deaths += next.deaths;
-                    acc.active += next.active;
-                    acc
-                },
-            );
-
-            to_return.push(folded);
-        });
-
-        YearlyCases(to_return)
+        self.fold_by(|daily| daily.year)
+            .into_iter()
+            .map(|(year, totals)| YearlyCase {
+                year,
+                positive: totals.positive,
+                recovered: totals.recovered,
+                deaths: totals.deaths,
+                active: totals.active,
+            })
+            .collect()
     }
 
     /// Aggregate daily into yearly format and pick 1 specific year.
@@ -230,6 +213,39 @@ impl FromIterator<DailyCase> for DailyCases {
     }
 }
 
+impl FromIterator<MonthlyCase> for MonthlyCases {
+    fn from_iter<T: IntoIterator<Item = MonthlyCase>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl FromIterator<YearlyCase> for YearlyCases {
+    fn from_iter<T: IntoIterator<Item = YearlyCase>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// Sum of the metrics of a group of daily cases.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaseTotals {
+    /// Number of daily cases summed up.
+    pub days: u32,
+    pub positive: i32,
+    pub recovered: i32,
+    pub deaths: i32,
+    pub active: i32,
+}
+
+impl CaseTotals {
+    pub fn add(&mut self, daily: &DailyCase) {
+        self.days += 1;
+        self.positive += daily.positive;
+        self.recovered += daily.recovered;
+        self.deaths += daily.deaths;
+        self.active += daily.active;
+    }
+}
+
 #[derive(Serialize, Deserialize, Component)]
 #[component(example = json!({
     "year": 2021,
@@ -250,6 +266,42 @@ pub struct DailyCase {
     pub active: i32,
 }
 
+impl DailyCase {
+    pub fn date(&self) -> NaiveDate {
+        NaiveDate::from_ymd(self.year, self.month, self.day)
+    }
+
+    /// Values of the metrics, in the same order as [`crate::fields::METRICS`].
+    pub fn metrics(&self) -> [i32; 4] {
+        [self.positive, self.recovered, self.deaths, self.active]
+    }
+}
+
+/// A day of a contiguous daily series, either a daily case or a day missing from the source.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Component)]
+#[component(example = json!({
+    "year": 2022,
+    "month": 5,
+    "day": 3,
+    "positive": 312,
+    "recovered": 296,
+    "deaths": 7,
+    "active": 9,
+    "filled": true
+}))]
+pub struct FilledDailyCase {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    /// `null` when a missing day is filled with `fill=null`.
+    pub positive: Option<i32>,
+    pub recovered: Option<i32>,
+    pub deaths: Option<i32>,
+    pub active: Option<i32>,
+    /// Whether the day is missing from the source and was filled in.
+    pub filled: bool,
+}
+
 #[derive(Serialize, Deserialize, Component)]
 #[component(example = json!({
     "year": 2021,
@@ -284,10 +336,235 @@ pub struct YearlyCase {
     pub active: i32,
 }
 
+/// Sum of daily cases over an arbitrary date range.
+#[derive(Serialize, Deserialize, Debug, Clone, Component)]
+#[component(example = json!({
+    "since": "2021-06-15",
+    "upto": "2021-08-10",
+    "days": 57,
+    "positive": 1656725,
+    "recovered": 1471683,
+    "deaths": 72437,
+    "active": 112605
+}))]
+pub struct AggregatedCase {
+    /// First day of the range, in ISO 8601 format (YYYY-MM-DD).
+    pub since: String,
+    /// Last day of the range, in ISO 8601 format (YYYY-MM-DD).
+    pub upto: String,
+    /// Number of daily cases within the range.
+    pub days: u32,
+    pub positive: i32,
+    pub recovered: i32,
+    pub deaths: i32,
+    pub active: i32,
+}
+
+/// Aggregated cases of two periods along with the change from the baseline to the current one.
+#[derive(Serialize, Deserialize, Debug, Clone, Component)]
+#[component(example = json!({
+    "current": {
+        "since": "2021-07-01",
+        "upto": "2021-07-31",
+        "days": 31,
+        "positive": 1231011,
+        "recovered": 1013186,
+        "deaths": 35828,
+        "active": 181997
+    },
+    "baseline": {
+        "since": "2021-05-31",
+        "upto": "2021-06-30",
+        "days": 31,
+        "positive": 338254,
+        "recovered": 242016,
+        "deaths": 7949,
+        "active": 88289
+    },
+    "delta": {
+        "positive": { "absolute": 892757, "percentage": 263.93 },
+        "recovered": { "absolute": 771170, "percentage": 318.64 },
+        "deaths": { "absolute": 27879, "percentage": 350.72 },
+        "active": { "absolute": 93708, "percentage": 106.14 }
+    }
+}))]
+pub struct CaseComparison {
+    pub current: AggregatedCase,
+    pub baseline: AggregatedCase,
+    pub delta: CaseDeltas,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Component)]
+pub struct CaseDeltas {
+    pub positive: Delta,
+    pub recovered: Delta,
+    pub deaths: Delta,
+    pub active: Delta,
+}
+
+/// Change of a metric from the baseline period to the current one.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Component)]
+pub struct Delta {
+    pub absolute: i32,
+    /// Relative to the baseline, `null` when the baseline is 0.
+    pub percentage: Option<f64>,
+}
+
+/// Highest values of each metric and the waves of cases detected across the whole history.
+#[derive(Serialize, Deserialize, Debug, Clone, Component)]
+pub struct Peaks {
+    pub records: MetricRecords,
+    pub waves: Vec<Wave>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Component)]
+pub struct MetricRecords {
+    pub positive: Records,
+    pub deaths: Records,
+    pub active: Records,
+}
+
+/// Highest daily, weekly (Monday to Sunday), and monthly values of a metric.
+#[derive(Serialize, Deserialize, Debug, Clone, Component)]
+pub struct Records {
+    pub daily: Record,
+    pub weekly: Record,
+    pub monthly: Record,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Component)]
+#[component(example = json!({
+    "since": "2021-07-12",
+    "upto": "2021-07-18",
+    "value": 350273
+}))]
+pub struct Record {
+    pub since: String,
+    pub upto: String,
+    pub value: i32,
+}
+
+/// A wave of cases, from the lowest point before its peak to the lowest point after it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Component)]
+#[component(example = json!({
+    "start": "2021-05-14",
+    "peak": "2021-07-15",
+    "end": "2021-10-20",
+    "peak_value": 49645.71,
+    "prominence": 49312.57
+}))]
+pub struct Wave {
+    pub start: String,
+    pub peak: String,
+    pub end: String,
+    /// Smoothed value of the metric at the peak.
+    pub peak_value: f64,
+    /// How much the peak stands out from the lowest point separating it from a higher peak.
+    pub prominence: f64,
+}
+
+/// Kind of a data-quality issue.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Component)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityFlagKind {
+    /// One or more days are missing right before the flagged date.
+    Gap,
+    /// A metric is negative.
+    Negative,
+    /// A metric deviates strongly from the surrounding days, e.g. a batch-reporting spike.
+    Outlier,
+    /// A metric of a past day was revised by the source API's latest update, i.e. it changed
+    /// since the previous snapshot.
+    Revision,
+}
+
+/// A data-quality issue found on a daily case.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Component)]
+#[component(example = json!({
+    "date": "2021-02-25",
+    "kind": "negative",
+    "metric": "active",
+    "detail": "Negative value -457"
+}))]
+pub struct QualityFlag {
+    pub date: String,
+    pub kind: QualityFlagKind,
+    /// Affected metric, omitted for gaps.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metric: Option<String>,
+    pub detail: String,
+}
+
+/// Data-quality issues found across the whole history.
+#[derive(Serialize, Deserialize, Debug, Clone, Component)]
+pub struct QualityReport {
+    /// Number of daily cases checked.
+    pub days: u32,
+    /// Number of days missing between the earliest and the latest daily case.
+    pub missing_days: u32,
+    pub counts: QualityCounts,
+    pub flags: Vec<QualityFlag>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Component)]
+pub struct QualityCounts {
+    pub gap: u32,
+    pub negative: u32,
+    pub outlier: u32,
+    pub revision: u32,
+}
+
+/// A stored response of the source API.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Component)]
+#[component(example = json!({
+    "id": 1661958000000_i64,
+    "fetched_at": "2022-08-31T15:00:00+00:00"
+}))]
+pub struct Snapshot {
+    /// Time the response was fetched at, in milliseconds since the Unix epoch.
+    pub id: i64,
+    pub fetched_at: String,
+}
+
+/// Changes of the daily cases from one snapshot to a later one.
+#[derive(Serialize, Deserialize, Debug, Clone, Component)]
+pub struct SnapshotDiff {
+    pub from: Snapshot,
+    pub to: Snapshot,
+    /// Number of days only present in the later snapshot.
+    pub added_days: u32,
+    /// Number of days only present in the earlier snapshot.
+    pub removed_days: u32,
+    pub revisions: Vec<DailyRevision>,
+}
+
+/// A metric of a past day that changed between two snapshots.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Component)]
+#[component(example = json!({
+    "date": "2021-09-01",
+    "metric": "positive",
+    "before": 10534,
+    "after": 10784
+}))]
+pub struct DailyRevision {
+    pub date: String,
+    pub metric: String,
+    pub before: i32,
+    pub after: i32,
+}
+
 #[derive(Deserialize)]
 pub struct QueryParams {
     pub since: Option<String>,
     pub upto: Option<String>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    pub order: Option<SortOrder>,
+    pub fields: Option<String>,
+    pub last: Option<String>,
+    pub flags: Option<bool>,
+    pub fill: Option<Fill>,
+    pub as_of: Option<i64>,
 }
 
 pub mod source_api {
@@ -295,7 +572,7 @@ pub mod source_api {
     use chrono::{DateTime, Datelike};
     use serde::{Deserialize, Serialize};
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct SourceAPIResponse {
         pub update: Update,
     }
@@ -368,14 +645,14 @@ pub mod source_api {
         }
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct Update {
         pub harian: Vec<Harian>,
         pub total: Total,
         pub penambahan: Penambahan,
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct Harian {
         pub key_as_string: String,
         pub key: u64,
@@ -390,12 +667,12 @@ pub mod source_api {
         pub jumlah_dirawat_kum: HarianKeyValue,
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct HarianKeyValue {
         pub value: i32,
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct Total {
         pub jumlah_positif: u32,
         pub jumlah_dirawat: u32,
@@ -403,7 +680,7 @@ pub mod source_api {
         pub jumlah_meninggal: u32,
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    #[derive(Serialize, Deserialize, Debug, Clone)]
     pub struct Penambahan {
         pub jumlah_positif: i64,
         pub jumlah_meninggal: i64,
@@ -413,3 +690,26 @@ pub mod source_api {
         pub created: String,
     }
 }
+
+/// Usage of an API key since the server started.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Component)]
+#[component(example = json!({
+    "name": "partner",
+    "scopes": ["daily", "monthly"],
+    "daily_quota": 1000,
+    "requests_today": 42,
+    "total_requests": 1337,
+    "rejected_requests": 3,
+    "last_used_at": "2022-08-31T15:00:00+00:00"
+}))]
+pub struct KeyUsage {
+    pub name: String,
+    pub scopes: Vec<AuthScope>,
+    pub daily_quota: Option<u64>,
+    /// Requests made today (UTC), which count against the quota.
+    pub requests_today: u64,
+    pub total_requests: u64,
+    /// Requests rejected for being out of the key's scopes or over its quota.
+    pub rejected_requests: u64,
+    pub last_used_at: Option<String>,
+}
@@ -0,0 +1,229 @@
+use std::{
+    error::Error,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+use reqwest::{
+    header::{HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    Client, StatusCode,
+};
+use tracing::Instrument;
+
+use crate::{
+    config::{self, UpstreamConfig},
+    middleware::{RequestId, REQUEST_ID_HEADER},
+};
+
+/// Sent to the source API so that it can tell our requests apart.
+pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+static CIRCUIT_BREAKER: OnceLock<CircuitBreaker> = OnceLock::new();
+
+/// Validators of a downloaded body, sent along with the next request so that the body is only
+/// downloaded again once it changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+pub enum Download {
+    Modified {
+        body: Vec<u8>,
+        validators: Validators,
+    },
+    /// The body didn't change since it was downloaded with the validators sent along.
+    NotModified,
+}
+
+/// Stops the source API from being fetched for a while once it failed too many times in a row,
+/// rather than making every request wait for it to fail again.
+///
+/// Once the cooldown is over, fetches are attempted again. The first failure opens the breaker
+/// again, the first success closes it.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<CircuitState>,
+}
+
+#[derive(Default)]
+struct CircuitState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: Mutex::new(CircuitState::default()),
+        }
+    }
+
+    /// When the source API may be fetched again, `None` when it may be fetched at `now`.
+    pub fn open_until(&self, now: Instant) -> Option<Instant> {
+        let state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        state.open_until.filter(|open_until| now < *open_until)
+    }
+
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap_or_else(|err| err.into_inner()) = CircuitState::default();
+    }
+
+    pub fn record_failure(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if state.consecutive_failures >= self.threshold {
+            state.open_until = Some(now + self.cooldown);
+        }
+    }
+}
+
+/// Client shared by every fetch of the source API, so that its connections are reused.
+pub fn client() -> &'static Client {
+    CLIENT.get_or_init(|| {
+        let upstream = &config::get().upstream;
+
+        Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(upstream.connect_timeout())
+            .timeout(upstream.timeout())
+            .build()
+            .expect("Failed building the HTTP client")
+    })
+}
+
+fn circuit_breaker() -> &'static CircuitBreaker {
+    CIRCUIT_BREAKER.get_or_init(|| {
+        let upstream = &config::get().upstream;
+        CircuitBreaker::new(
+            upstream.circuit_breaker_threshold,
+            upstream.circuit_breaker_cooldown(),
+        )
+    })
+}
+
+/// Delay before the `retry`-th retry (from 1): the base backoff doubled for every previous retry,
+/// randomly shortened by up to half so that clients don't retry in lockstep.
+pub fn backoff_delay(backoff: Duration, retry: u32) -> Duration {
+    let delay = backoff.saturating_mul(2_u32.saturating_pow(retry.saturating_sub(1)));
+    delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+}
+
+/// Whether a failed fetch may succeed when retried.
+fn is_transient(err: &reqwest::Error) -> bool {
+    match err.status() {
+        Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+        None => !err.is_builder(),
+    }
+}
+
+/// Download the body of the source API at `upstream.url`, retrying the transient failures with a
+/// jittered exponential backoff, unless the circuit breaker is open.<br>
+/// With `validators`, the body is only downloaded if it changed since.
+pub async fn download(
+    upstream: &UpstreamConfig,
+    validators: Option<&Validators>,
+) -> Result<Download, String> {
+    let circuit_breaker = circuit_breaker();
+    if let Some(open_until) = circuit_breaker.open_until(Instant::now()) {
+        return Err(format!(
+            "Skipping the source API for {}s after {} failed fetches in a row",
+            open_until
+                .saturating_duration_since(Instant::now())
+                .as_secs()
+                .max(1),
+            upstream.circuit_breaker_threshold
+        ));
+    }
+
+    let mut retry = 0;
+    loop {
+        let result = download_once(&upstream.url, validators)
+            .instrument(tracing::info_span!("upstream_fetch", url = %upstream.url, retry))
+            .await;
+
+        match result {
+            Ok(download) => {
+                circuit_breaker.record_success();
+                return Ok(download);
+            }
+            Err(err) if retry < upstream.max_retries && is_transient(&err) => {
+                retry += 1;
+                let delay = backoff_delay(upstream.retry_backoff(), retry);
+                tracing::warn!(
+                    error = %describe(&err),
+                    retry,
+                    delay_ms = delay.as_millis() as u64,
+                    "Retrying fetch from source API"
+                );
+                actix_web::rt::time::sleep(delay).await;
+            }
+            Err(err) => {
+                circuit_breaker.record_failure(Instant::now());
+                return Err(describe(&err));
+            }
+        }
+    }
+}
+
+/// `err` followed by its causes, down to the root one, e.g. a refused connection.
+fn describe(err: &dyn Error) -> String {
+    let mut description = err.to_string();
+
+    let mut source = err.source();
+    while let Some(cause) = source {
+        let cause_description = cause.to_string();
+        if !description.contains(&cause_description) {
+            description = format!("{description}: {cause_description}");
+        }
+        source = cause.source();
+    }
+
+    description
+}
+
+async fn download_once(
+    url: &str,
+    validators: Option<&Validators>,
+) -> Result<Download, reqwest::Error> {
+    // Sent along so that the source API's logs can be correlated with ours.
+    let mut request = client().get(url);
+    if let Some(request_id) = RequestId::current() {
+        request = request.header(REQUEST_ID_HEADER, request_id.0);
+    }
+    if let Some(validators) = validators {
+        if let Some(etag) = &validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let resp = request.send().await?.error_for_status()?;
+    if resp.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Download::NotModified);
+    }
+
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|value: &HeaderValue| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let validators = Validators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+
+    Ok(Download::Modified {
+        body: resp.bytes().await?.to_vec(),
+        validators,
+    })
+}
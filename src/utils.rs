@@ -1,13 +1,186 @@
-use crate::types;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Mutex,
+    time::Instant,
+};
+
+use chrono::Utc;
+
+use crate::{
+    cache, config,
+    single_flight::SingleFlight,
+    snapshots::SnapshotStore,
+    storage::SharedStorage,
+    types::source_api::SourceAPIResponse,
+    upstream::{self, Download, Validators},
+};
 
 pub static COVID_API_ENDPOINT: &str = "https://data.covid19.go.id/public/api/update.json";
 
-pub async fn fetch_data_from_source_api() -> Result<types::source_api::SourceAPIResponse, String> {
-    let resp = reqwest::get(COVID_API_ENDPOINT)
-        .await
-        .map_err(|_| "Failed fetching data from source API.")?;
+/// When the source API's data was last ingested into the storage.
+static LAST_INGESTED_AT: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// The source API's latest response and its validators, reused as long as it isn't modified.
+static LAST_DOWNLOADED: Mutex<Option<(Validators, SourceAPIResponse)>> = Mutex::new(None);
+
+/// Hash of the source API's latest body, telling whether a new body actually changed the data.
+static LAST_BODY_HASH: Mutex<Option<u64>> = Mutex::new(None);
+
+static SOURCE_FETCHES: SingleFlight<(), Result<(SourceAPIResponse, bool), String>> =
+    SingleFlight::new();
+
+/// Download the raw body of the configured source API, ignoring `upstream.source_file`.
+pub async fn download_from_source_api() -> Result<Vec<u8>, String> {
+    match download_if_modified(None).await? {
+        Download::Modified { body, .. } => Ok(body),
+        Download::NotModified => {
+            Err("Source API answered an unconditional request with 304 Not Modified".to_string())
+        }
+    }
+}
+
+async fn download_if_modified(validators: Option<&Validators>) -> Result<Download, String> {
+    let upstream = &config::get().upstream;
+
+    upstream::download(upstream, validators).await.map_err(|err| {
+        tracing::error!(url = %upstream.url, error = %err, "Failed fetching data from source API");
+        format!("Failed fetching data from source API: {err}")
+    })
+}
+
+/// The source API's response, or the latest snapshot when it's unavailable.
+pub async fn fetch_data_from_source_api() -> Result<SourceAPIResponse, String> {
+    fetch_source().await.map(|(source, _)| source)
+}
+
+/// The source API's response, and whether it was freshly fetched rather than read from the latest
+/// snapshot because the source API is unavailable.<br>
+/// Concurrent calls share a single fetch.
+async fn fetch_source() -> Result<(SourceAPIResponse, bool), String> {
+    SOURCE_FETCHES.run((), fetch_source_now).await
+}
+
+async fn fetch_source_now() -> Result<(SourceAPIResponse, bool), String> {
+    let mut downloaded_validators = None;
+    let body = match &config::get().upstream.source_file {
+        Some(path) => std::fs::read(path).map_err(|err| {
+            tracing::error!(path = %path, error = %err, "Failed reading data from source file");
+            format!("Failed reading data from source file: {err}")
+        })?,
+        None => {
+            let validators = LAST_DOWNLOADED
+                .lock()
+                .map_err(|err| err.to_string())?
+                .as_ref()
+                .map(|(validators, _)| validators.clone());
+
+            match download_if_modified(validators.as_ref()).await {
+                Ok(Download::Modified { body, validators }) => {
+                    downloaded_validators = Some(validators);
+                    body
+                }
+                // Only ever answered when validators were sent, i.e. the source was downloaded.
+                Ok(Download::NotModified) => {
+                    return match LAST_DOWNLOADED
+                        .lock()
+                        .map_err(|err| err.to_string())?
+                        .as_ref()
+                    {
+                        Some((_, source)) => Ok((source.clone(), true)),
+                        None => Err("Source API answered 304 Not Modified".to_string()),
+                    };
+                }
+                Err(err) => return latest_snapshot().map(|source| (source, false)).ok_or(err),
+            }
+        }
+    };
+
+    let json = tracing::info_span!("parse_source", bytes = body.len())
+        .in_scope(|| serde_json::from_slice::<SourceAPIResponse>(&body))
+        .map_err(|err| {
+            tracing::error!(error = %err, "Failed processing data from source API");
+            format!("Failed processing data: {err}")
+        })?;
+
+    // Keeping the revision history is best-effort, it mustn't fail the request.
+    if let Err(err) = SnapshotStore::from_config().save(&body, Utc::now()) {
+        tracing::warn!(error = %err, "Failed saving snapshot");
+    }
+
+    if let Some(validators) = downloaded_validators {
+        let last_downloaded =
+            (validators != Validators::default()).then(|| (validators, json.clone()));
+        *LAST_DOWNLOADED.lock().map_err(|err| err.to_string())? = last_downloaded;
+    }
+
+    // Without validators the source API answers every request in full, even when nothing changed.
+    if is_new_body(&body)? {
+        // The data changed, so the storage is outdated and the cached responses are too.
+        *LAST_INGESTED_AT.lock().map_err(|err| err.to_string())? = None;
+        cache::warm_up_in_background(&json);
+    }
+
+    Ok((json, true))
+}
+
+/// Whether `body` differs from the previous body of the source API, remembering it for the next one.
+fn is_new_body(body: &[u8]) -> Result<bool, String> {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    let mut last_hash = LAST_BODY_HASH.lock().map_err(|err| err.to_string())?;
+    Ok(last_hash.replace(hash) != Some(hash))
+}
+
+fn latest_snapshot() -> Option<SourceAPIResponse> {
+    let snapshots = SnapshotStore::from_config();
+    let snapshot = snapshots
+        .latest()
+        .and_then(|latest| latest.map(|latest| snapshots.load(latest.id)).transpose())
+        .map_err(|err| tracing::warn!(error = %err, "Failed reading the latest snapshot"))
+        .ok()
+        .flatten()
+        .flatten()?;
+
+    tracing::warn!("Serving the latest snapshot, the source API is unavailable");
+    Some(snapshot)
+}
+
+/// Fetch the latest data from the source API and upsert its daily cases into `storage`.
+///
+/// The source API isn't fetched again within the configured refresh interval, as long as the
+/// storage holds data. When it's unavailable, the storage is left as is so that the stored
+/// history keeps being served, unless nothing was ever stored.
+#[tracing::instrument(skip_all)]
+pub async fn ingest_into_storage(storage: &SharedStorage) -> Result<(), String> {
+    let refresh_interval = config::get().upstream.refresh_interval();
+    let is_fresh = LAST_INGESTED_AT
+        .lock()
+        .map_err(|err| err.to_string())?
+        .is_some_and(|ingested_at| ingested_at.elapsed() < refresh_interval);
+    let is_empty = storage
+        .query(|storage| storage.latest_date().map(|latest| latest.is_none()))
+        .await?;
+    if is_fresh && !is_empty {
+        return Ok(());
+    }
 
-    let json = resp.json().await.map_err(|_| "Failed processing data.")?;
+    match fetch_source().await {
+        Ok((source, is_fresh)) => {
+            let daily_cases = source.to_daily();
+            storage
+                .query(move |storage| storage.upsert_daily_cases(&daily_cases))
+                .await?;
+            // Data read from a snapshot is refreshed as soon as the source API is back.
+            if is_fresh {
+                *LAST_INGESTED_AT.lock().map_err(|err| err.to_string())? = Some(Instant::now());
+            }
+        }
+        Err(err) if is_empty => return Err(err),
+        Err(_) => tracing::warn!("Serving the stored data, the source API is unavailable"),
+    }
 
-    Ok(json)
+    Ok(())
 }
@@ -0,0 +1,155 @@
+mod common;
+
+use actix_web::{test, web, App};
+use chrono::{naive::MAX_DATE, Duration};
+use rust_covid_api::{
+    aggregation::Buckets,
+    date_range::Period,
+    routes::{aggregate, monthly},
+    types::{AggregatedCase, MonthlyCase},
+};
+
+mod buckets {
+    use super::{Buckets, Duration, Period, MAX_DATE};
+
+    #[test]
+    fn clamps_the_buckets_ending_beyond_the_supported_dates() {
+        let since = MAX_DATE - Duration::days(10);
+
+        for size in [Period::Days(100), Period::Weeks(100), Period::Years(100)] {
+            let buckets = Buckets::new(since, MAX_DATE, Some(size));
+
+            assert_eq!(buckets.bounds(0), (since, MAX_DATE), "{size:?}");
+        }
+    }
+}
+
+mod aggregate_range {
+    use actix_web_lab::middleware::from_fn;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn returns_the_same_totals_as_the_monthly_endpoint() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/aggregate").service(aggregate::aggregate))
+                .service(
+                    web::scope("/monthly")
+                        .wrap(from_fn(monthly::middleware::filter_malformed_query_params))
+                        .service(monthly::specific_month),
+                ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/aggregate?since=2021-03-01&upto=2021-03-31")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "application/json"
+        );
+        let aggregated: AggregatedCase = test::read_body_json(resp).await;
+
+        let req = test::TestRequest::get().uri("/monthly/2021/3").to_request();
+        let monthly: MonthlyCase = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(aggregated.since, "2021-03-01");
+        assert_eq!(aggregated.upto, "2021-03-31");
+        assert_eq!(aggregated.days, 31);
+        assert_eq!(aggregated.positive, monthly.positive);
+        assert_eq!(aggregated.recovered, monthly.recovered);
+        assert_eq!(aggregated.deaths, monthly.deaths);
+        assert_eq!(aggregated.active, monthly.active);
+    }
+
+    #[actix_web::test]
+    async fn splits_the_range_into_buckets() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/aggregate").service(aggregate::aggregate)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/aggregate?since=2021-06-15&upto=2021-08-10&bucket=10d")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        let body: Vec<AggregatedCase> = test::read_body_json(resp).await;
+
+        assert_eq!(body.len(), 6);
+        assert_eq!(
+            (body[0].since.as_str(), body[0].upto.as_str(), body[0].days),
+            ("2021-06-15", "2021-06-24", 10)
+        );
+        let last_bucket = body.last().unwrap();
+        assert_eq!(
+            (
+                last_bucket.since.as_str(),
+                last_bucket.upto.as_str(),
+                last_bucket.days
+            ),
+            ("2021-08-04", "2021-08-10", 7)
+        );
+    }
+
+    #[actix_web::test]
+    async fn returns_400_given_malformed_bucket() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/aggregate").service(aggregate::aggregate)),
+        )
+        .await;
+
+        for uri in [
+            "/aggregate?bucket=ten-days",
+            "/aggregate?bucket=999999999d",
+            "/aggregate?bucket=101y",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+
+            assert_eq!(resp.status().as_u16(), 400, "{uri}");
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/aggregate?bucket=100y")
+            .to_request();
+        let body: Vec<AggregatedCase> = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn returns_404_given_range_without_cases() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/aggregate").service(aggregate::aggregate)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/aggregate?since=2019-01-01&upto=2019-12-31")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 404);
+    }
+}
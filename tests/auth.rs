@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use actix_web::{dev::Service, http::StatusCode, test, web, App, HttpResponse};
+use rust_covid_api::{
+    auth::{ApiKeys, AuthError},
+    config::{ApiKeyConfig, AuthConfig, AuthScope},
+    middleware::Authenticate,
+    rate_limit::API_KEY_HEADER,
+    routes,
+    types::KeyUsage,
+};
+
+fn auth_config(allow_anonymous: bool) -> AuthConfig {
+    AuthConfig {
+        enabled: true,
+        allow_anonymous,
+        anonymous_scopes: vec![AuthScope::Daily],
+        keys: vec![
+            ApiKeyConfig {
+                name: "partner".to_string(),
+                key: "partner-key".to_string(),
+                scopes: vec![AuthScope::Daily, AuthScope::Monthly, AuthScope::Exports],
+                daily_quota: Some(2),
+            },
+            ApiKeyConfig {
+                name: "operator".to_string(),
+                key: "operator-key".to_string(),
+                scopes: vec![AuthScope::Admin],
+                daily_quota: None,
+            },
+        ],
+        ..AuthConfig::default()
+    }
+}
+
+macro_rules! init_app {
+    ($allow_anonymous:expr) => {{
+        let api_keys = Arc::new(ApiKeys::from_config(&auth_config($allow_anonymous)).unwrap());
+
+        test::init_service(
+            App::new()
+                .app_data(web::Data::from(api_keys.clone()))
+                .wrap(Authenticate::new(Some(api_keys)))
+                .route("/", web::get().to(HttpResponse::Ok))
+                .route("/daily", web::get().to(HttpResponse::Ok))
+                .route("/aggregate", web::get().to(HttpResponse::Ok))
+                .route("/yearly", web::get().to(HttpResponse::Ok))
+                .route("/health", web::get().to(HttpResponse::Ok))
+                .route("/admin/usage", web::get().to(routes::admin::key_usage)),
+        )
+        .await
+    }};
+}
+
+/// Status of the response to a request for `uri`, or of the error it was rejected with.
+macro_rules! status_of {
+    ($app:expr, $uri:expr, $api_key:expr) => {{
+        let mut req = test::TestRequest::get().uri($uri);
+        let api_key: Option<&str> = $api_key;
+        if let Some(api_key) = api_key {
+            req = req.insert_header((API_KEY_HEADER, api_key));
+        }
+
+        match $app.call(req.to_request()).await {
+            Ok(res) => res.status(),
+            Err(err) => err.error_response().status(),
+        }
+    }};
+}
+
+mod api_keys {
+    use super::{auth_config, ApiKeys, AuthConfig, AuthError, AuthScope};
+
+    #[test]
+    fn authorizes_keys_within_their_scopes_and_quota() {
+        let api_keys = ApiKeys::from_config(&auth_config(true)).unwrap();
+
+        assert!(api_keys
+            .authorize(Some("partner-key"), &[AuthScope::Monthly])
+            .is_ok());
+        assert!(matches!(
+            api_keys.authorize(Some("partner-key"), &[AuthScope::Yearly]),
+            Err(AuthError::OutOfScope(AuthScope::Yearly))
+        ));
+        assert!(api_keys
+            .authorize(Some("partner-key"), &[AuthScope::Daily, AuthScope::Exports])
+            .is_ok());
+        assert!(matches!(
+            api_keys.authorize(Some("partner-key"), &[]),
+            Err(AuthError::QuotaExceeded(2))
+        ));
+        assert!(matches!(
+            api_keys.authorize(Some("unknown-key"), &[]),
+            Err(AuthError::InvalidKey)
+        ));
+
+        let usage = api_keys.usage();
+        assert_eq!(
+            usage.iter().map(|usage| &usage.name).collect::<Vec<_>>(),
+            vec!["operator", "partner"]
+        );
+        assert_eq!(usage[1].requests_today, 2);
+        assert_eq!(usage[1].total_requests, 2);
+        assert_eq!(usage[1].rejected_requests, 2);
+        assert!(usage[1].last_used_at.is_some());
+        assert!(usage[0].last_used_at.is_none());
+    }
+
+    #[test]
+    fn limits_anonymous_requests_to_their_scopes() {
+        let api_keys = ApiKeys::from_config(&auth_config(true)).unwrap();
+
+        assert!(api_keys.authorize(None, &[AuthScope::Daily]).is_ok());
+        assert!(api_keys.authorize(None, &[]).is_ok());
+        assert!(matches!(
+            api_keys.authorize(None, &[AuthScope::Daily, AuthScope::Exports]),
+            Err(AuthError::OutOfScope(AuthScope::Exports))
+        ));
+
+        let api_keys = ApiKeys::from_config(&auth_config(false)).unwrap();
+
+        assert!(matches!(
+            api_keys.authorize(None, &[AuthScope::Daily]),
+            Err(AuthError::MissingKey)
+        ));
+    }
+
+    #[test]
+    fn reads_the_keys_file() {
+        let keys_file = std::env::temp_dir().join("rust-covid-api-test-keys.toml");
+        std::fs::write(
+            &keys_file,
+            "[[keys]]\nname = \"other\"\nkey = \"other-key\"\nscopes = [\"yearly\"]\n",
+        )
+        .unwrap();
+
+        let api_keys = ApiKeys::from_config(&AuthConfig {
+            keys_file: Some(keys_file.to_string_lossy().to_string()),
+            ..auth_config(false)
+        })
+        .unwrap();
+
+        assert!(api_keys
+            .authorize(Some("other-key"), &[AuthScope::Yearly])
+            .is_ok());
+        assert_eq!(api_keys.usage().len(), 3);
+    }
+}
+
+mod authenticate {
+    use super::*;
+
+    #[actix_web::test]
+    async fn rejects_requests_out_of_scope() {
+        let app = init_app!(true);
+
+        assert_eq!(status_of!(app, "/daily", None), StatusCode::OK);
+        assert_eq!(status_of!(app, "/yearly", None), StatusCode::FORBIDDEN);
+        assert_eq!(
+            status_of!(app, "/yearly", Some("partner-key")),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status_of!(app, "/daily", Some("wrong-key")),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/daily")
+            .insert_header(("Accept", "text/csv"))
+            .to_request();
+        let res = app.call(req).await.unwrap_err().error_response();
+
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            actix_web::body::to_bytes(res.into_body()).await.unwrap(),
+            "Not allowed to access exports"
+        );
+    }
+
+    #[actix_web::test]
+    async fn scopes_the_routes_computed_from_the_daily_cases() {
+        let app = init_app!(false);
+
+        for uri in ["/", "/aggregate"] {
+            assert_eq!(status_of!(app, uri, Some("partner-key")), StatusCode::OK);
+            assert_eq!(
+                status_of!(app, uri, Some("operator-key")),
+                StatusCode::FORBIDDEN
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn requires_a_key_unless_anonymous_requests_are_allowed() {
+        let app = init_app!(false);
+
+        assert_eq!(status_of!(app, "/daily", None), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status_of!(app, "/daily", Some("partner-key")),
+            StatusCode::OK
+        );
+        assert_eq!(status_of!(app, "/health", None), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn rejects_requests_over_the_quota() {
+        let app = init_app!(false);
+
+        for _ in 0..2 {
+            status_of!(app, "/daily", Some("partner-key"));
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/daily")
+            .insert_header((API_KEY_HEADER, "partner-key"))
+            .to_request();
+        let res = app.call(req).await.unwrap_err().error_response();
+
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(res.headers().contains_key("retry-after"));
+    }
+
+    #[actix_web::test]
+    async fn reports_the_usage_to_admins() {
+        let app = init_app!(false);
+
+        status_of!(app, "/daily", Some("partner-key"));
+        assert_eq!(
+            status_of!(app, "/admin/usage", Some("partner-key")),
+            StatusCode::FORBIDDEN
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/admin/usage")
+            .insert_header((API_KEY_HEADER, "operator-key"))
+            .to_request();
+        let usage: Vec<KeyUsage> = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(usage[1].name, "partner");
+        assert_eq!(usage[1].total_requests, 1);
+        assert_eq!(usage[1].rejected_requests, 1);
+    }
+}
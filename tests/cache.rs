@@ -0,0 +1,236 @@
+use std::sync::{Arc, Mutex};
+
+use actix_web::{
+    dev::Service, http::StatusCode, test, web, App, HttpRequest, HttpResponse, HttpServer,
+};
+use rust_covid_api::{
+    auth::ApiKeys,
+    cache::{self, CACHE_REFRESH_HEADER},
+    compression::ContentEncoding,
+    config::{AuthConfig, RateLimitConfig, ServerConfig},
+    middleware::{Authenticate, RateLimit},
+    rate_limit::RateLimiter,
+    response::ResponseFormat,
+    types::{DailyCase, DailyCases},
+};
+
+fn api_keys() -> Arc<ApiKeys> {
+    let config = AuthConfig {
+        enabled: true,
+        allow_anonymous: false,
+        ..AuthConfig::default()
+    };
+
+    Arc::new(ApiKeys::from_config(&config).unwrap())
+}
+
+fn limiter() -> Arc<RateLimiter> {
+    let config = RateLimitConfig {
+        enabled: true,
+        burst: 1,
+        requests_per_minute: 1,
+        ..RateLimitConfig::default()
+    };
+
+    Arc::new(RateLimiter::new(config, "redis://127.0.0.1/").unwrap())
+}
+
+/// Uri, `Accept` and `Accept-Encoding` headers of a request.
+type Received = (String, String, String);
+
+/// Run a server that requires an API key and allows a single request, and collect the requests
+/// it answers.
+fn serve() -> (ServerConfig, Arc<Mutex<Vec<Received>>>) {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let (api_keys, limiter) = (api_keys(), limiter());
+
+    let received = requests.clone();
+    let server = HttpServer::new(move || {
+        let received = received.clone();
+        App::new()
+            .wrap(Authenticate::new(Some(api_keys.clone())))
+            .wrap(RateLimit::new(Some(limiter.clone())))
+            .default_service(web::to(move |req: HttpRequest| {
+                let header = |name| {
+                    req.headers()
+                        .get(name)
+                        .unwrap()
+                        .to_str()
+                        .unwrap()
+                        .to_string()
+                };
+                received.lock().unwrap().push((
+                    req.uri().to_string(),
+                    header("accept"),
+                    header("accept-encoding"),
+                ));
+                HttpResponse::Ok()
+            }))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+
+    let server_config = ServerConfig {
+        bind_address: "0.0.0.0".to_string(),
+        port: server.addrs()[0].port(),
+    };
+    actix_web::rt::spawn(server.run());
+
+    (server_config, requests)
+}
+
+fn daily_case(year: i32, month: u32) -> DailyCase {
+    DailyCase {
+        year,
+        month,
+        day: 1,
+        positive: 1,
+        recovered: 0,
+        deaths: 0,
+        active: 1,
+    }
+}
+
+mod refresh {
+    use super::*;
+
+    #[actix_web::test]
+    async fn requests_the_response_again_past_authentication_and_rate_limits() {
+        let (server_config, requests) = serve();
+
+        for _ in 0..2 {
+            cache::refresh(
+                &server_config,
+                "/daily?fields=date",
+                ResponseFormat::Csv,
+                ContentEncoding::Gzip,
+            )
+            .await
+            .unwrap();
+        }
+        let requests = requests.lock().unwrap();
+
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].0, "/daily?fields=date");
+        assert_eq!(requests[0].1, "text/csv; charset=utf-8");
+        assert_eq!(requests[0].2, "gzip");
+    }
+
+    #[actix_web::test]
+    async fn rejects_forged_refreshes() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Authenticate::new(Some(api_keys())))
+                .route("/daily", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/daily")
+            .insert_header((CACHE_REFRESH_HEADER, "forged"))
+            .to_request();
+        let res = app.call(req).await.unwrap_err().error_response();
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+}
+
+mod warm_up_paths {
+    use super::{cache, daily_case, DailyCases};
+
+    #[test]
+    fn covers_every_year_and_the_recent_months() {
+        let daily_cases = DailyCases(vec![
+            daily_case(2020, 12),
+            daily_case(2021, 1),
+            daily_case(2021, 2),
+            daily_case(2021, 2),
+        ]);
+
+        assert_eq!(
+            cache::warm_up_paths(&daily_cases, 2),
+            vec![
+                "/",
+                "/daily",
+                "/monthly",
+                "/yearly",
+                "/monthly/2020",
+                "/daily/2020",
+                "/monthly/2021",
+                "/daily/2021",
+                "/monthly/2021/2",
+                "/daily/2021/2",
+                "/monthly/2021/1",
+                "/daily/2021/1",
+            ]
+        );
+    }
+}
+
+mod warm_up {
+    use super::*;
+
+    #[actix_web::test]
+    async fn caches_the_json_responses_in_every_encoding() {
+        let (server_config, requests) = serve();
+        let paths = vec!["/daily".to_string(), "/yearly".to_string()];
+
+        assert_eq!(cache::warm_up(&server_config, &paths).await, 8);
+
+        let mut requests = requests.lock().unwrap().clone();
+        requests.sort();
+        assert_eq!(requests.len(), 8);
+        assert!(requests
+            .iter()
+            .all(|(_, accept, _)| accept == "application/json"));
+        assert_eq!(
+            requests
+                .iter()
+                .map(|(uri, _, encoding)| format!("{uri} {encoding}"))
+                .take(4)
+                .collect::<Vec<_>>(),
+            vec!["/daily br", "/daily gzip", "/daily identity", "/daily zstd"]
+        );
+    }
+}
+
+mod response_cache {
+    use rust_covid_api::{
+        cache::RedisCache,
+        config::{self, CacheBackend, CacheConfig, Config},
+        middleware::CacheResponse,
+    };
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn serves_uncached_responses_when_redis_is_unavailable() {
+        // Nothing listens on port 1, so connecting is refused right away.
+        let redis_url = "redis://127.0.0.1:1/";
+        config::init(Config {
+            cache: CacheConfig {
+                backend: CacheBackend::Redis,
+                redis_url: redis_url.into(),
+                ..CacheConfig::default()
+            },
+            ..Config::default()
+        });
+        let redis_cache = RedisCache::new(redis::Client::open(redis_url).unwrap());
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(redis_cache))
+                .wrap(CacheResponse)
+                .route("/daily", web::get().to(|| async { "[]" })),
+        )
+        .await;
+
+        for _ in 0..2 {
+            let req = test::TestRequest::get().uri("/daily").to_request();
+            let res = test::call_service(&app, req).await;
+
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(test::read_body(res).await, "[]");
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use actix_web::web;
+use rust_covid_api::storage::SharedStorage;
+
+/// Latest date in the fixture dataset, as (year, month, day).
+#[allow(dead_code)]
+pub const LATEST_DATE: (i32, u32, u32) = (2022, 8, 31);
+
+/// Serve the fixture dataset instead of the source API, so that tests run offline and deterministically.
+///
+/// Every storage is a fresh in-memory database filled from the fixture, and the snapshots are kept
+/// apart from the ones of a locally running server.
+pub fn use_fixture() {
+    std::env::set_var(
+        "SOURCE_FILE",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/update.json"),
+    );
+    std::env::set_var("DATABASE_PATH", ":memory:");
+    std::env::set_var(
+        "SNAPSHOTS_DIR",
+        std::env::temp_dir().join("rust-covid-api-test-snapshots"),
+    );
+}
+
+/// Storage to serve an app under test with, a fresh in-memory one given [`use_fixture`].
+#[allow(dead_code)]
+pub fn storage() -> web::Data<SharedStorage> {
+    web::Data::new(SharedStorage::open_from_config().unwrap())
+}
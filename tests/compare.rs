@@ -0,0 +1,110 @@
+mod common;
+
+use actix_web::{test, web, App};
+use rust_covid_api::{
+    routes::{aggregate, compare},
+    types::{AggregatedCase, CaseComparison},
+};
+
+mod compare_ranges {
+    use super::*;
+
+    #[actix_web::test]
+    async fn compares_with_the_previous_period_by_default() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/aggregate").service(aggregate::aggregate))
+                .service(web::scope("/compare").service(compare::compare)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/compare?since=2021-07-11&upto=2021-07-20")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        let comparison: CaseComparison = test::read_body_json(resp).await;
+
+        let req = test::TestRequest::get()
+            .uri("/aggregate?since=2021-07-01&upto=2021-07-10")
+            .to_request();
+        let previous: AggregatedCase = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(comparison.baseline.since, "2021-07-01");
+        assert_eq!(comparison.baseline.upto, "2021-07-10");
+        assert_eq!(comparison.baseline.positive, previous.positive);
+        assert_eq!(
+            comparison.delta.positive.absolute,
+            comparison.current.positive - previous.positive
+        );
+    }
+
+    #[actix_web::test]
+    async fn compares_with_the_same_period_a_year_ago() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/compare").service(compare::compare)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/compare?since=2021-07-01&upto=2021-07-31&vs=year_ago")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        let comparison: CaseComparison = test::read_body_json(resp).await;
+
+        assert_eq!(comparison.baseline.since, "2020-07-01");
+        assert_eq!(comparison.baseline.upto, "2020-07-31");
+        assert_eq!(comparison.baseline.days, 31);
+    }
+
+    #[actix_web::test]
+    async fn returns_400_given_both_vs_and_explicit_baseline() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/compare").service(compare::compare)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/compare?vs=previous&vs_since=2021-01-01&vs_upto=2021-01-31")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 400);
+    }
+
+    #[actix_web::test]
+    async fn returns_400_given_a_baseline_ending_before_it_starts() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/compare").service(compare::compare)),
+        )
+        .await;
+
+        for uri in [
+            "/compare?vs_since=2021-01-31&vs_upto=2021-01-01",
+            "/compare?vs_since=today-10&vs_upto=today-20",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+
+            assert_eq!(resp.status().as_u16(), 400, "{uri}");
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::io::Read;
+
+use actix_web::http::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING};
+use rust_covid_api::compression::ContentEncoding;
+
+fn accept_encoding(value: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT_ENCODING, HeaderValue::from_str(value).unwrap());
+    headers
+}
+
+mod negotiate {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_identity_without_header() {
+        assert_eq!(
+            ContentEncoding::negotiate(&HeaderMap::new()),
+            ContentEncoding::Identity
+        );
+    }
+
+    #[test]
+    fn prefers_brotli_among_equally_weighted_codings() {
+        let headers = accept_encoding("gzip, deflate, br");
+        assert_eq!(
+            ContentEncoding::negotiate(&headers),
+            ContentEncoding::Brotli
+        );
+    }
+
+    #[test]
+    fn respects_quality_values() {
+        let headers = accept_encoding("br;q=0.2, gzip;q=0.8, zstd;q=0");
+        assert_eq!(ContentEncoding::negotiate(&headers), ContentEncoding::Gzip);
+    }
+
+    #[test]
+    fn falls_back_to_identity_given_unsupported_codings() {
+        let headers = accept_encoding("deflate, compress");
+        assert_eq!(
+            ContentEncoding::negotiate(&headers),
+            ContentEncoding::Identity
+        );
+    }
+}
+
+mod compress {
+    use super::*;
+
+    const PAYLOAD: &[u8] = br#"[{"year":2021,"month":2,"day":25,"positive":8493}]"#;
+
+    #[test]
+    fn gzip_round_trips() {
+        let compressed = ContentEncoding::Gzip.compress(PAYLOAD).unwrap();
+
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(compressed.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, PAYLOAD);
+    }
+
+    #[test]
+    fn brotli_round_trips() {
+        let compressed = ContentEncoding::Brotli.compress(PAYLOAD).unwrap();
+
+        let mut decompressed = Vec::new();
+        brotli::Decompressor::new(compressed.as_slice(), 4096)
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, PAYLOAD);
+    }
+
+    #[test]
+    fn zstd_round_trips() {
+        let compressed = ContentEncoding::Zstd.compress(PAYLOAD).unwrap();
+
+        let decompressed = zstd::decode_all(compressed.as_slice()).unwrap();
+        assert_eq!(decompressed, PAYLOAD);
+    }
+}
+
+mod middleware {
+    use std::io::Read;
+
+    use actix_web::{
+        http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, VARY},
+        test, web, App, HttpResponse,
+    };
+    use rust_covid_api::{
+        config::{self, CacheBackend, CacheConfig, Config},
+        middleware::{CacheResponse, Compress},
+    };
+
+    const PAYLOAD: &str = r#"[{"year":2021,"month":2,"day":25,"positive":8493}]"#;
+
+    #[actix_web::test]
+    async fn compresses_responses_when_caching_is_disabled() {
+        config::init(Config {
+            cache: CacheConfig {
+                backend: CacheBackend::None,
+                ..CacheConfig::default()
+            },
+            ..Config::default()
+        });
+        let app = test::init_service(App::new().wrap(Compress).wrap(CacheResponse).route(
+            "/daily",
+            web::get().to(|| async { HttpResponse::Ok().body(PAYLOAD) }),
+        ))
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/daily")
+            .insert_header((ACCEPT_ENCODING, "gzip"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(res.headers().get(VARY).unwrap(), "Accept, Accept-Encoding");
+        let body = test::read_body(res).await;
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(body.as_ref())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, PAYLOAD);
+
+        let req = test::TestRequest::get().uri("/daily").to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert!(res.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(test::read_body(res).await, PAYLOAD);
+    }
+}
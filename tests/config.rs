@@ -0,0 +1,142 @@
+use rust_covid_api::config::{CacheBackend, Config, ConfigError, LogFormat};
+
+fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+    vars.iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+mod from_sources {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid() {
+        let config = Config::from_sources(None, Vec::new()).unwrap();
+
+        assert_eq!(config, Config::default());
+        assert_eq!(config.server.port, 8082);
+        assert_eq!(config.cache.ttl_secs, 600);
+        assert_eq!(config.cache.stale_ttl_secs, 3600);
+    }
+
+    #[test]
+    fn reads_the_config_file() {
+        let contents = r#"
+            [server]
+            port = 9000
+
+            [cache]
+            backend = "none"
+            ttl_secs = 60
+
+            [cache.route_ttl_secs]
+            "/quality" = 3600
+
+            [logging]
+            format = "json"
+        "#;
+
+        let config = Config::from_sources(Some(contents), Vec::new()).unwrap();
+
+        assert_eq!(config.server.port, 9000);
+        assert_eq!(config.server.bind_address, "0.0.0.0");
+        assert_eq!(config.cache.backend, CacheBackend::None);
+        assert_eq!(config.cache.ttl_secs_of("/quality"), 3600);
+        assert_eq!(config.cache.ttl_secs_of("/daily"), 60);
+        assert_eq!(config.logging.format, LogFormat::Json);
+    }
+
+    #[test]
+    fn environment_variables_override_the_config_file() {
+        let contents = "[server]\nport = 9000\n\n[cache]\nttl_secs = 60\n";
+
+        let config = Config::from_sources(
+            Some(contents),
+            vars(&[
+                ("COVID_API__SERVER__PORT", "9001"),
+                ("COVID_API__CACHE__TTL_SECS", "120"),
+                (
+                    "COVID_API__CORS__ALLOWED_ORIGINS",
+                    r#"["https://example.com"]"#,
+                ),
+                (
+                    "COVID_API__UPSTREAM__URL",
+                    "http://localhost:8000/update.json",
+                ),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(config.server.port, 9001);
+        assert_eq!(config.cache.ttl_secs, 120);
+        assert_eq!(config.cors.allowed_origins, vec!["https://example.com"]);
+        assert_eq!(config.upstream.url, "http://localhost:8000/update.json");
+    }
+
+    #[test]
+    fn legacy_variables_are_overridden_by_prefixed_ones() {
+        let config = Config::from_sources(
+            None,
+            vars(&[
+                ("PORT", "9000"),
+                ("DATABASE_PATH", ":memory:"),
+                ("SOURCE_FILE", ""),
+                ("COVID_API__SERVER__PORT", "9001"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(config.server.port, 9001);
+        assert_eq!(config.storage.database_path, ":memory:");
+        assert_eq!(config.upstream.source_file, None);
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_mistyped_values() {
+        for contents in ["[server]\nprot = 9000\n", "[server]\nport = \"nine\"\n"] {
+            assert!(matches!(
+                Config::from_sources(Some(contents), Vec::new()),
+                Err(ConfigError::Malformed(_))
+            ));
+        }
+
+        assert!(matches!(
+            Config::from_sources(None, vars(&[("PORT", "99999")])),
+            Err(ConfigError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn reports_every_invalid_value() {
+        let contents = r#"
+            [server]
+            bind_address = "somewhere"
+
+            [upstream]
+            url = "ftp://example.com/update.json"
+            connect_timeout_secs = 10
+            timeout_secs = 5
+
+            [cache]
+            ttl_secs = 0
+
+            [cors]
+            allowed_origins = ["https://example.com/path"]
+        "#;
+
+        let err = match Config::from_sources(Some(contents), Vec::new()) {
+            Err(ConfigError::Invalid(err)) => err,
+            result => panic!("Expected an invalid config, got {result:?}"),
+        };
+
+        for key in [
+            "server.bind_address",
+            "upstream.url",
+            "upstream.connect_timeout_secs",
+            "cache.ttl_secs",
+            "cors.allowed_origins",
+        ] {
+            assert!(err.contains(key), "`{key}` isn't reported in: {err}");
+        }
+    }
+}
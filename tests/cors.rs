@@ -0,0 +1,169 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceRequest, ServiceResponse},
+    error::ErrorUnauthorized,
+    http::{Method, StatusCode},
+    test, web, App, HttpResponse,
+};
+use actix_web_lab::middleware::{from_fn, Next};
+use rust_covid_api::{config::CorsConfig, middleware::Cors};
+
+async fn reject(
+    _: ServiceRequest,
+    _: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    Err::<ServiceResponse, _>(ErrorUnauthorized("Invalid API key"))
+}
+
+macro_rules! init_app {
+    ($allowed_origins:expr) => {
+        test::init_service(
+            App::new()
+                .wrap(Cors::new(CorsConfig {
+                    allowed_origins: $allowed_origins
+                        .iter()
+                        .map(|origin: &&str| origin.to_string())
+                        .collect(),
+                    ..CorsConfig::default()
+                }))
+                .route("/daily", web::get().to(HttpResponse::Ok))
+                .service(
+                    web::scope("/rejected")
+                        .wrap(from_fn(reject))
+                        .route("", web::get().to(HttpResponse::Ok)),
+                ),
+        )
+        .await
+    };
+}
+
+fn preflight(origin: &str, method: &str, headers: &str) -> test::TestRequest {
+    test::TestRequest::default()
+        .method(Method::OPTIONS)
+        .uri("/daily")
+        .insert_header(("Origin", origin))
+        .insert_header(("Access-Control-Request-Method", method))
+        .insert_header(("Access-Control-Request-Headers", headers))
+}
+
+mod cors {
+    use super::*;
+
+    #[actix_web::test]
+    async fn answers_the_preflight_requests_of_allowed_origins() {
+        let app = init_app!(["https://dashboard.example.com"]);
+
+        let req = preflight("https://dashboard.example.com", "GET", "x-api-key, accept");
+        let res = test::call_service(&app, req.to_request()).await;
+
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let headers = res.headers();
+        assert_eq!(
+            headers.get("access-control-allow-origin").unwrap(),
+            "https://dashboard.example.com"
+        );
+        assert_eq!(
+            headers.get("access-control-allow-methods").unwrap(),
+            "GET, HEAD"
+        );
+        assert_eq!(headers.get("access-control-max-age").unwrap(), "3600");
+        assert!(headers.contains_key("access-control-allow-headers"));
+    }
+
+    #[actix_web::test]
+    async fn rejects_disallowed_preflight_requests() {
+        let app = init_app!(["https://dashboard.example.com"]);
+
+        for (req, reason) in [
+            (
+                preflight("https://elsewhere.example.com", "GET", ""),
+                "Origin not allowed",
+            ),
+            (
+                preflight("https://dashboard.example.com", "DELETE", ""),
+                "Method DELETE not allowed",
+            ),
+            (
+                preflight("https://dashboard.example.com", "GET", "x-secret"),
+                "Header x-secret not allowed",
+            ),
+        ] {
+            let res = test::call_service(&app, req.to_request()).await;
+
+            assert_eq!(res.status(), StatusCode::FORBIDDEN);
+            assert!(!res.headers().contains_key("access-control-allow-origin"));
+            assert_eq!(test::read_body(res).await, reason);
+        }
+    }
+
+    #[actix_web::test]
+    async fn lets_allowed_origins_read_responses_and_errors() {
+        let app = init_app!(["https://dashboard.example.com"]);
+
+        let req = test::TestRequest::get()
+            .uri("/daily")
+            .insert_header(("Origin", "https://dashboard.example.com"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(
+            res.headers().get("access-control-allow-origin").unwrap(),
+            "https://dashboard.example.com"
+        );
+        assert!(res
+            .headers()
+            .get("access-control-expose-headers")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .contains("X-Request-Id"));
+        assert_eq!(res.headers().get("vary").unwrap(), "Origin");
+
+        let req = test::TestRequest::get()
+            .uri("/rejected")
+            .insert_header(("Origin", "https://dashboard.example.com"))
+            .to_request();
+        let res = app.call(req).await.unwrap_err().error_response();
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers().get("access-control-allow-origin").unwrap(),
+            "https://dashboard.example.com"
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/daily")
+            .insert_header(("Origin", "https://elsewhere.example.com"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert!(!res.headers().contains_key("access-control-allow-origin"));
+    }
+
+    #[actix_web::test]
+    async fn allows_any_origin_with_a_wildcard() {
+        let app = init_app!(["*"]);
+
+        let req = test::TestRequest::get()
+            .uri("/daily")
+            .insert_header(("Origin", "https://anywhere.example.com"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(
+            res.headers().get("access-control-allow-origin").unwrap(),
+            "*"
+        );
+        assert!(!res.headers().contains_key("vary"));
+    }
+
+    #[actix_web::test]
+    async fn does_nothing_without_allowed_origins() {
+        let app = init_app!([] as [&str; 0]);
+
+        let req = preflight("https://dashboard.example.com", "GET", "");
+        let res = test::call_service(&app, req.to_request()).await;
+
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,24 +1,30 @@
+mod common;
+
 use actix_web::{test, web, App};
-use rust_covid_api::{routes::daily, types::DailyCase};
+use rust_covid_api::{
+    routes::daily,
+    types::{DailyCase, FilledDailyCase},
+};
 
 mod all_days {
+    use actix_web::dev::Service;
     use actix_web_lab::middleware::from_fn;
-    use chrono::Datelike;
+    use chrono::{Duration, NaiveDate};
 
     use super::*;
 
     #[actix_web::test]
     async fn returns_all_days() {
-        let current_time = chrono::Utc::now();
-        let current_year = current_time.year();
-        let current_month = current_time.month();
+        common::use_fixture();
+
+        let (latest_year, latest_month, latest_day) = common::LATEST_DATE;
 
         let earliest_year = 2020;
         let earliest_month = 3;
         let earliest_day = 2;
 
         let app = test::init_service(
-            App::new().service(
+            App::new().app_data(common::storage()).service(
                 web::scope("/daily")
                     .wrap(from_fn(daily::middleware::filter_malformed_query_params))
                     .service(daily::all_days),
@@ -42,10 +48,264 @@ mod all_days {
         assert_eq!(body[0].day, earliest_day);
 
         let last_item = body.last().unwrap();
-        assert_eq!(last_item.year, current_year);
-        assert_eq!(last_item.month, current_month);
-        // We don't assert the day of the last item in response body because
-        // there's a chance the API hasn't updated its data to the current day.
+        assert_eq!(last_item.year, latest_year);
+        assert_eq!(last_item.month, latest_month);
+        assert_eq!(last_item.day, latest_day);
+    }
+
+    #[actix_web::test]
+    async fn returns_requested_page_in_descending_order() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new().app_data(common::storage()).service(
+                web::scope("/daily")
+                    .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                    .service(daily::all_days),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/daily?since=2021-03-01&upto=2021-03-31&order=desc&limit=7&offset=7")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(resp.headers().get("X-Total-Count").unwrap(), "31");
+
+        let link = resp.headers().get("Link").unwrap().to_str().unwrap();
+        assert!(link.contains("limit=7&offset=0>; rel=\"prev\""));
+        assert!(link.contains("limit=7&offset=14>; rel=\"next\""));
+        assert!(link.contains("limit=7&offset=28>; rel=\"last\""));
+
+        let body: Vec<DailyCase> = test::read_body_json(resp).await;
+        assert_eq!(body.len(), 7);
+        assert_eq!((body[0].month, body[0].day), (3, 24));
+        assert_eq!((body[6].month, body[6].day), (3, 18));
+    }
+
+    #[actix_web::test]
+    async fn returns_an_empty_page_given_the_largest_offset() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new().app_data(common::storage()).service(
+                web::scope("/daily")
+                    .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                    .service(daily::all_days),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/daily?offset=18446744073709551615&limit=10")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+
+        let link = resp.headers().get("Link").unwrap().to_str().unwrap();
+        assert!(!link.contains("rel=\"next\""));
+
+        let body: Vec<DailyCase> = test::read_body_json(resp).await;
+        assert!(body.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn returns_400_given_zero_limit() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new().app_data(common::storage()).service(
+                web::scope("/daily")
+                    .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                    .service(daily::all_days),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/daily?limit=0").to_request();
+        let err = app.call(req).await.unwrap_err();
+
+        assert_eq!(err.error_response().status().as_u16(), 400);
+    }
+
+    #[actix_web::test]
+    async fn returns_days_relative_to_the_latest_date() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new().app_data(common::storage()).service(
+                web::scope("/daily")
+                    .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                    .service(daily::all_days),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/daily?last=2w").to_request();
+        let last_two_weeks: Vec<DailyCase> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(last_two_weeks.len(), 14);
+
+        let req = test::TestRequest::get()
+            .uri("/daily?since=today-13")
+            .to_request();
+        let since_13_days_ago: Vec<DailyCase> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(since_13_days_ago.len(), 14);
+        assert_eq!(last_two_weeks[0].date(), since_13_days_ago[0].date());
+
+        // The longest ranges allowed cover all the data.
+        let req = test::TestRequest::get().uri("/daily").to_request();
+        let all_days: Vec<DailyCase> = test::call_and_read_body_json(&app, req).await;
+        for uri in ["/daily?last=1000y", "/daily?since=today-366000"] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let days: Vec<DailyCase> = test::call_and_read_body_json(&app, req).await;
+            assert_eq!(days.len(), all_days.len(), "{uri}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn returns_400_given_malformed_relative_range() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new().app_data(common::storage()).service(
+                web::scope("/daily")
+                    .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                    .service(daily::all_days),
+            ),
+        )
+        .await;
+
+        for uri in [
+            "/daily?since=today-x",
+            "/daily?last=3q",
+            "/daily?last=0d",
+            "/daily?since=today-4000000000",
+            "/daily?last=4000000000d",
+            "/daily?last=999999999m",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let err = app.call(req).await.unwrap_err();
+
+            assert_eq!(err.error_response().status().as_u16(), 400, "{uri}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn returns_400_given_unknown_field() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new().app_data(common::storage()).service(
+                web::scope("/daily")
+                    .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                    .service(daily::all_days),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/daily?fields=positive,hospitalized")
+            .to_request();
+        let err = app.call(req).await.unwrap_err();
+
+        assert_eq!(err.error_response().status().as_u16(), 400);
+    }
+
+    #[actix_web::test]
+    async fn returns_a_contiguous_series_given_fill() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new().app_data(common::storage()).service(
+                web::scope("/daily")
+                    .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                    .service(daily::all_days),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/daily").to_request();
+        let daily_cases: Vec<DailyCase> = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri("/daily?fill=interpolate")
+            .to_request();
+        let series: Vec<FilledDailyCase> = test::call_and_read_body_json(&app, req).await;
+
+        let date_of =
+            |daily: &FilledDailyCase| NaiveDate::from_ymd(daily.year, daily.month, daily.day);
+        assert!(series
+            .iter()
+            .zip(series.iter().skip(1))
+            .all(|(previous, next)| date_of(next) - date_of(previous) == Duration::days(1)));
+        assert_eq!(
+            series.iter().filter(|daily| !daily.filled).count(),
+            daily_cases.len()
+        );
+        assert!(series
+            .iter()
+            .filter(|daily| daily.filled)
+            .all(|daily| daily.positive.is_some()));
+    }
+
+    #[actix_web::test]
+    async fn returns_400_given_unknown_fill() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new().app_data(common::storage()).service(
+                web::scope("/daily")
+                    .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                    .service(daily::all_days),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/daily?fill=previous")
+            .to_request();
+        let err = app.call(req).await.unwrap_err();
+
+        assert_eq!(err.error_response().status().as_u16(), 400);
+    }
+}
+
+mod latest_day {
+    use actix_web_lab::middleware::from_fn;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn returns_the_most_recent_day() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new().app_data(common::storage()).service(
+                web::scope("/daily")
+                    .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                    .service(daily::all_days)
+                    .service(daily::latest_day),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/daily/latest").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        let latest: DailyCase = test::read_body_json(resp).await;
+
+        let req = test::TestRequest::get()
+            .uri("/daily?order=desc&limit=1")
+            .to_request();
+        let body: Vec<DailyCase> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(
+            (latest.year, latest.month, latest.day),
+            (body[0].year, body[0].month, body[0].day)
+        );
     }
 }
 
@@ -56,8 +316,11 @@ mod all_days_in_a_year {
 
     #[actix_web::test]
     async fn returns_all_days_in_a_year() {
+        common::use_fixture();
+
         let app = test::init_service(
             App::new()
+                .app_data(common::storage())
                 .wrap(from_fn(daily::middleware::filter_malformed_query_params))
                 .service(web::scope("/daily").service(daily::all_days_in_a_year)),
         )
@@ -82,8 +345,11 @@ mod all_days_in_a_year {
 
     #[actix_web::test]
     async fn returns_404_given_invalid_year() {
+        common::use_fixture();
+
         let app = test::init_service(
             App::new()
+                .app_data(common::storage())
                 .wrap(from_fn(daily::middleware::filter_malformed_query_params))
                 .service(web::scope("/daily").service(daily::all_days_in_a_year)),
         )
@@ -103,8 +369,11 @@ mod all_days_in_a_month {
 
     #[actix_web::test]
     async fn returns_all_days_in_a_month() {
+        common::use_fixture();
+
         let app = test::init_service(
             App::new()
+                .app_data(common::storage())
                 .wrap(from_fn(daily::middleware::filter_malformed_query_params))
                 .service(web::scope("/daily").service(daily::all_days_in_a_month)),
         )
@@ -135,8 +404,11 @@ mod all_days_in_a_month {
 
     #[actix_web::test]
     async fn returns_404_given_invalid_month() {
+        common::use_fixture();
+
         let app = test::init_service(
             App::new()
+                .app_data(common::storage())
                 .wrap(from_fn(daily::middleware::filter_malformed_query_params))
                 .service(web::scope("/daily").service(daily::all_days_in_a_month)),
         )
@@ -156,8 +428,11 @@ mod specific_day {
 
     #[actix_web::test]
     async fn returns_200_given_valid_day() {
+        common::use_fixture();
+
         let app = test::init_service(
             App::new()
+                .app_data(common::storage())
                 .wrap(from_fn(daily::middleware::filter_malformed_query_params))
                 .service(web::scope("/daily").service(daily::specific_day)),
         )
@@ -183,10 +458,37 @@ mod specific_day {
         assert_eq!(body.day, chosen_day);
     }
 
+    #[actix_web::test]
+    async fn returns_only_selected_fields() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                .service(web::scope("/daily").service(daily::specific_day)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/daily/2021/2/26?fields=deaths,positive")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+
+        let body: serde_json::Map<String, serde_json::Value> = test::read_body_json(resp).await;
+        let keys = body.keys().map(String::as_str).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["year", "month", "day", "positive", "deaths"]);
+    }
+
     #[actix_web::test]
     async fn returns_404_given_invalid_day() {
+        common::use_fixture();
+
         let app = test::init_service(
             App::new()
+                .app_data(common::storage())
                 .wrap(from_fn(daily::middleware::filter_malformed_query_params))
                 .service(web::scope("/daily").service(daily::specific_day)),
         )
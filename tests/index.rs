@@ -1,8 +1,12 @@
+mod common;
+
 use actix_web::{test, web, App};
 use rust_covid_api::routes::{self, index::CasesSummary};
 
 #[actix_web::test]
 async fn has_valid_response_structure() {
+    common::use_fixture();
+
     let app = test::init_service(
         App::new().route("/", web::get().to(routes::index::daily_cases_summary)),
     )
@@ -0,0 +1,95 @@
+mod common;
+
+use actix_web::{test, web, App};
+use rust_covid_api::{
+    routes::{daily, insights},
+    types::{DailyCase, Peaks},
+};
+
+mod peaks {
+    use actix_web_lab::middleware::from_fn;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn returns_the_highest_daily_values() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/insights").service(insights::peaks))
+                .service(
+                    web::scope("/daily")
+                        .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                        .service(daily::all_days),
+                ),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/insights/peaks").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        let peaks: Peaks = test::read_body_json(resp).await;
+
+        let req = test::TestRequest::get().uri("/daily").to_request();
+        let daily_cases: Vec<DailyCase> = test::call_and_read_body_json(&app, req).await;
+        let highest_positive = daily_cases.iter().map(|daily| daily.positive).max();
+        let highest_deaths = daily_cases.iter().map(|daily| daily.deaths).max();
+
+        let records = peaks.records;
+        assert_eq!(Some(records.positive.daily.value), highest_positive);
+        assert_eq!(records.positive.daily.since, records.positive.daily.upto);
+        assert_eq!(Some(records.deaths.daily.value), highest_deaths);
+        assert!(records.positive.weekly.value >= records.positive.daily.value);
+        assert!(records.positive.monthly.value >= records.positive.weekly.value);
+    }
+
+    #[actix_web::test]
+    async fn detects_waves_in_chronological_order() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/insights").service(insights::peaks)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/insights/peaks?metric=positive&prominence=0.5")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        let peaks: Peaks = test::read_body_json(resp).await;
+
+        assert!(!peaks.waves.is_empty());
+        for wave in &peaks.waves {
+            assert!(wave.start <= wave.peak && wave.peak <= wave.end);
+        }
+        for (previous, next) in peaks.waves.iter().zip(peaks.waves.iter().skip(1)) {
+            assert!(previous.end <= next.start);
+        }
+    }
+
+    #[actix_web::test]
+    async fn returns_400_given_out_of_range_prominence() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/insights").service(insights::peaks)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/insights/peaks?prominence=1.5")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 400);
+    }
+}
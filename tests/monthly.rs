@@ -1,23 +1,25 @@
+mod common;
+
 use actix_web::{test, web, App};
 use rust_covid_api::{routes::monthly, types::MonthlyCase};
 
 mod all_months {
     use actix_web_lab::middleware::from_fn;
-    use chrono::Datelike;
 
     use super::*;
 
     #[actix_web::test]
     async fn returns_all_months() {
-        let current_time = chrono::Utc::now();
-        let current_year = current_time.year();
-        let current_month = current_time.month();
+        common::use_fixture();
+
+        let (latest_year, latest_month, _) = common::LATEST_DATE;
 
         let earliest_year = 2020;
         let earliest_month = 3;
 
         let app = test::init_service(
             App::new()
+                .app_data(common::storage())
                 .wrap(from_fn(monthly::middleware::filter_malformed_query_params))
                 .service(web::scope("/monthly").service(monthly::all_months)),
         )
@@ -38,8 +40,49 @@ mod all_months {
         assert_eq!(body[0].month, earliest_month);
 
         let last_item = body.last().unwrap();
-        assert_eq!(last_item.year, current_year);
-        assert_eq!(last_item.month, current_month);
+        assert_eq!(last_item.year, latest_year);
+        assert_eq!(last_item.month, latest_month);
+    }
+}
+
+mod all_months_relative_range {
+    use actix_web_lab::middleware::from_fn;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn returns_the_latest_months() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .wrap(from_fn(monthly::middleware::filter_malformed_query_params))
+                .service(web::scope("/monthly").service(monthly::all_months)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/monthly").to_request();
+        let all_months: Vec<MonthlyCase> = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get()
+            .uri("/monthly?last=6m")
+            .to_request();
+        let last_six_months: Vec<MonthlyCase> = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(last_six_months.len(), 6);
+        let latest = all_months.last().unwrap();
+        let last_item = last_six_months.last().unwrap();
+        assert_eq!(
+            (last_item.year, last_item.month),
+            (latest.year, latest.month)
+        );
+
+        let req = test::TestRequest::get()
+            .uri("/monthly?last=12000m")
+            .to_request();
+        let longest: Vec<MonthlyCase> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(longest.len(), all_months.len());
     }
 }
 
@@ -50,8 +93,11 @@ mod all_months_in_a_year {
 
     #[actix_web::test]
     async fn returns_all_months_in_a_year() {
+        common::use_fixture();
+
         let app = test::init_service(
             App::new()
+                .app_data(common::storage())
                 .wrap(from_fn(monthly::middleware::filter_malformed_query_params))
                 .service(web::scope("/monthly").service(monthly::all_months_in_a_year)),
         )
@@ -75,10 +121,38 @@ mod all_months_in_a_year {
         assert_eq!(body.last().unwrap().year, chosen_year);
     }
 
+    #[actix_web::test]
+    async fn returns_latest_months_first_given_desc_order() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .wrap(from_fn(monthly::middleware::filter_malformed_query_params))
+                .service(web::scope("/monthly").service(monthly::all_months_in_a_year)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/monthly/2021?order=desc&limit=3")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(resp.headers().get("X-Total-Count").unwrap(), "12");
+
+        let body: Vec<MonthlyCase> = test::read_body_json(resp).await;
+        let months = body.iter().map(|monthly| monthly.month).collect::<Vec<_>>();
+        assert_eq!(months, vec![12, 11, 10]);
+    }
+
     #[actix_web::test]
     async fn returns_404_given_invalid_year() {
+        common::use_fixture();
+
         let app = test::init_service(
             App::new()
+                .app_data(common::storage())
                 .wrap(from_fn(monthly::middleware::filter_malformed_query_params))
                 .service(web::scope("/monthly").service(monthly::all_months_in_a_year)),
         )
@@ -98,8 +172,11 @@ mod specific_month {
 
     #[actix_web::test]
     async fn returns_correct_data_given_valid_month() {
+        common::use_fixture();
+
         let app = test::init_service(
             App::new()
+                .app_data(common::storage())
                 .wrap(from_fn(monthly::middleware::filter_malformed_query_params))
                 .service(web::scope("/monthly").service(monthly::specific_month)),
         )
@@ -125,8 +202,11 @@ mod specific_month {
 
     #[actix_web::test]
     async fn returns_404_given_invalid_month() {
+        common::use_fixture();
+
         let app = test::init_service(
             App::new()
+                .app_data(common::storage())
                 .wrap(from_fn(monthly::middleware::filter_malformed_query_params))
                 .service(web::scope("/monthly").service(monthly::all_months_in_a_year)),
         )
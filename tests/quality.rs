@@ -0,0 +1,165 @@
+mod common;
+
+use actix_web::{test, web, App};
+use actix_web_lab::middleware::from_fn;
+use rust_covid_api::{
+    routes::{daily, quality},
+    types::{QualityFlag, QualityFlagKind, QualityReport},
+};
+use serde_json::Value;
+
+mod quality_report {
+    use super::*;
+
+    #[actix_web::test]
+    async fn counts_every_flag_in_date_order() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .route("/quality", web::get().to(quality::quality_report)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/quality").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        let report: QualityReport = test::read_body_json(resp).await;
+
+        let counts = report.counts;
+        assert_eq!(
+            (counts.gap + counts.negative + counts.outlier + counts.revision) as usize,
+            report.flags.len()
+        );
+        assert_eq!(counts.gap == 0, report.missing_days == 0);
+        assert!(report
+            .flags
+            .iter()
+            .zip(report.flags.iter().skip(1))
+            .all(|(previous, next)| previous.date <= next.date));
+    }
+
+    #[actix_web::test]
+    async fn flags_the_known_issues_of_the_fixture() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .route("/quality", web::get().to(quality::quality_report)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/quality").to_request();
+        let report: QualityReport = test::call_and_read_body_json(&app, req).await;
+
+        let has_flag = |date: &str, kind: QualityFlagKind, metric: Option<&str>| {
+            report.flags.iter().any(|flag| {
+                flag.date == date && flag.kind == kind && flag.metric.as_deref() == metric
+            })
+        };
+
+        assert_eq!(report.missing_days, 1);
+        assert!(has_flag("2022-05-04", QualityFlagKind::Gap, None));
+        assert!(has_flag(
+            "2021-11-09",
+            QualityFlagKind::Outlier,
+            Some("positive")
+        ));
+    }
+}
+
+mod revisions {
+    use rust_covid_api::types::{DailyCase, DailyCases, DailyRevision, QualityFlagKind};
+
+    #[test]
+    fn are_reported_along_with_the_flags_of_the_daily_cases() {
+        let daily_cases = DailyCases(vec![DailyCase {
+            year: 2021,
+            month: 9,
+            day: 1,
+            positive: 150,
+            recovered: 10,
+            deaths: 1,
+            active: -11,
+        }]);
+        let revisions = [DailyRevision {
+            date: "2021-09-01".to_string(),
+            metric: "positive".to_string(),
+            before: 120,
+            after: 150,
+        }];
+
+        let report = daily_cases.quality_report(&revisions);
+
+        assert_eq!((report.counts.negative, report.counts.revision), (1, 1));
+        assert_eq!(report.flags[1].kind, QualityFlagKind::Revision);
+        assert_eq!(report.flags[1].metric.as_deref(), Some("positive"));
+        assert_eq!(
+            report.flags[1].detail,
+            "Revised from 120 to 150 since the previous snapshot"
+        );
+    }
+}
+
+mod daily_flags {
+    use super::*;
+
+    #[actix_web::test]
+    async fn annotates_negative_values() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new().app_data(common::storage()).service(
+                web::scope("/daily")
+                    .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                    .service(daily::specific_day),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/daily/2021/2/25?flags=true")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        let body: Value = test::read_body_json(resp).await;
+        let flags: Vec<QualityFlag> = serde_json::from_value(body["flags"].clone()).unwrap();
+
+        assert!(body["active"].as_i64().unwrap() < 0);
+        assert!(flags
+            .iter()
+            .any(|flag| flag.kind == QualityFlagKind::Negative
+                && flag.metric.as_deref() == Some("active")
+                && flag.date == "2021-02-25"));
+    }
+
+    #[actix_web::test]
+    async fn leaves_out_flags_by_default() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new().app_data(common::storage()).service(
+                web::scope("/daily")
+                    .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                    .service(daily::all_days),
+            ),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/daily?since=2021-02-20&upto=2021-02-28")
+            .to_request();
+        let body: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+        assert!(body.iter().all(|daily| daily.get("flags").is_none()));
+
+        let req = test::TestRequest::get()
+            .uri("/daily?since=2021-02-20&upto=2021-02-28&flags=true")
+            .to_request();
+        let body: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+        assert!(body.iter().all(|daily| daily["flags"].is_array()));
+    }
+}
@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use actix_web::{test, web, App, HttpResponse};
+use rust_covid_api::{
+    auth::ApiKeys,
+    config::{ApiKeyConfig, AuthConfig, RateLimitConfig, RateLimitKey},
+    middleware::RateLimit,
+    rate_limit::{RateLimiter, TokenBucket, API_KEY_HEADER, MAX_MEMORY_BUCKETS},
+};
+
+fn limiter(key: RateLimitKey) -> Arc<RateLimiter> {
+    let config = RateLimitConfig {
+        enabled: true,
+        key,
+        burst: 2,
+        requests_per_minute: 6,
+        ..RateLimitConfig::default()
+    };
+
+    Arc::new(RateLimiter::new(config, "redis://127.0.0.1/").unwrap())
+}
+
+/// Keys named after their value.
+fn api_keys(keys: &[&str]) -> web::Data<ApiKeys> {
+    let config = AuthConfig {
+        enabled: true,
+        keys: keys
+            .iter()
+            .map(|key| ApiKeyConfig {
+                name: key.to_string(),
+                key: key.to_string(),
+                scopes: Vec::new(),
+                daily_quota: None,
+            })
+            .collect(),
+        ..AuthConfig::default()
+    };
+
+    web::Data::new(ApiKeys::from_config(&config).unwrap())
+}
+
+macro_rules! init_app {
+    ($limiter:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(api_keys(&["first", "second"]))
+                .wrap(RateLimit::new($limiter))
+                .route("/daily", web::get().to(HttpResponse::Ok))
+                .route("/health", web::get().to(HttpResponse::Ok)),
+        )
+        .await
+    };
+}
+
+mod token_bucket {
+    use super::TokenBucket;
+
+    #[test]
+    fn refills_over_time_up_to_its_capacity() {
+        // A token every 10 seconds.
+        let tokens_per_ms = 0.0001;
+        let mut bucket = TokenBucket::full(2, 0);
+
+        assert!(bucket.take(2, tokens_per_ms, 0));
+        assert!(bucket.take(2, tokens_per_ms, 0));
+        assert!(!bucket.take(2, tokens_per_ms, 9_999));
+        assert!(bucket.take(2, tokens_per_ms, 10_000));
+        assert_eq!(bucket.tokens_at(2, tokens_per_ms, 1_000_000), 2.0);
+    }
+}
+
+mod rate_limit {
+    use super::*;
+
+    #[actix_web::test]
+    async fn rejects_clients_out_of_tokens() {
+        let app = init_app!(Some(limiter(RateLimitKey::Ip)));
+
+        for remaining in ["1", "0"] {
+            let req = test::TestRequest::get().uri("/daily").to_request();
+            let res = test::call_service(&app, req).await;
+
+            assert_eq!(res.status(), 200);
+            assert_eq!(res.headers().get("ratelimit-limit").unwrap(), "2");
+            assert_eq!(res.headers().get("ratelimit-remaining").unwrap(), remaining);
+        }
+
+        let req = test::TestRequest::get().uri("/daily").to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), 429);
+        assert_eq!(res.headers().get("retry-after").unwrap(), "10");
+        assert_eq!(res.headers().get("ratelimit-remaining").unwrap(), "0");
+        assert_eq!(res.headers().get("ratelimit-reset").unwrap(), "20");
+        assert_eq!(
+            test::read_body(res).await,
+            "Too many requests, retry in 10 second(s)."
+        );
+    }
+
+    #[actix_web::test]
+    async fn limits_clients_separately() {
+        let app = init_app!(Some(limiter(RateLimitKey::ApiKey)));
+
+        for _ in 0..2 {
+            let req = test::TestRequest::get()
+                .uri("/daily")
+                .insert_header((API_KEY_HEADER, "first"))
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/daily")
+            .insert_header((API_KEY_HEADER, "second"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri("/daily")
+            .insert_header((API_KEY_HEADER, "first"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 429);
+    }
+
+    #[actix_web::test]
+    async fn limits_made_up_keys_by_ip() {
+        let app = init_app!(Some(limiter(RateLimitKey::ApiKey)));
+
+        for (api_key, status) in [("made-up", 200), ("other", 200), ("another", 429)] {
+            let req = test::TestRequest::get()
+                .uri("/daily")
+                .insert_header((API_KEY_HEADER, api_key))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), status);
+        }
+    }
+
+    #[actix_web::test]
+    async fn keeps_throttling_clients_when_full_of_buckets() {
+        let limiter = limiter(RateLimitKey::Ip);
+
+        for _ in 0..2 {
+            limiter.check("first").await;
+        }
+
+        for client in 0..MAX_MEMORY_BUCKETS {
+            assert!(limiter.check(&client.to_string()).await.allowed);
+        }
+
+        assert!(!limiter.check("first").await.allowed);
+        assert!(limiter.check("new").await.allowed);
+    }
+
+    #[actix_web::test]
+    async fn skips_exempt_paths_and_disabled_limits() {
+        let app = init_app!(Some(limiter(RateLimitKey::Ip)));
+
+        for _ in 0..3 {
+            let req = test::TestRequest::get().uri("/health").to_request();
+            let res = test::call_service(&app, req).await;
+
+            assert_eq!(res.status(), 200);
+            assert!(res.headers().get("ratelimit-limit").is_none());
+        }
+
+        let app = init_app!(None);
+
+        for _ in 0..3 {
+            let req = test::TestRequest::get().uri("/daily").to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), 200);
+        }
+    }
+}
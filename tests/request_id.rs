@@ -0,0 +1,122 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{Service, ServiceRequest, ServiceResponse},
+    error::ErrorBadRequest,
+    test, web, App, HttpResponse,
+};
+use actix_web_lab::middleware::{from_fn, Next};
+use rust_covid_api::middleware::{AssignRequestId, RequestId, REQUEST_ID_HEADER};
+
+async fn reject(
+    _: ServiceRequest,
+    _: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    Err::<ServiceResponse, _>(ErrorBadRequest("Malformed"))
+}
+
+macro_rules! init_app {
+    () => {
+        test::init_service(
+            App::new()
+                .wrap(AssignRequestId)
+                .route(
+                    "/current",
+                    web::get()
+                        .to(|| async { HttpResponse::Ok().body(RequestId::current().unwrap().0) }),
+                )
+                .route(
+                    "/missing",
+                    web::get().to(|| async {
+                        Err::<HttpResponse, _>(actix_web::error::ErrorNotFound("Missing"))
+                    }),
+                )
+                .service(
+                    web::scope("/rejected")
+                        .wrap(from_fn(reject))
+                        .route("", web::get().to(HttpResponse::Ok)),
+                ),
+        )
+        .await
+    };
+}
+
+mod assign_request_id {
+    use super::*;
+
+    #[actix_web::test]
+    async fn generates_an_id_when_missing() {
+        let app = init_app!();
+
+        let req = test::TestRequest::get().uri("/current").to_request();
+        let res = test::call_service(&app, req).await;
+
+        let header = res.headers().get(REQUEST_ID_HEADER).unwrap().to_owned();
+        assert_eq!(header.len(), 36);
+        assert_eq!(test::read_body(res).await, header.as_bytes());
+    }
+
+    #[actix_web::test]
+    async fn keeps_the_id_sent_by_the_client() {
+        let app = init_app!();
+
+        let req = test::TestRequest::get()
+            .uri("/current")
+            .insert_header((REQUEST_ID_HEADER, "client-id-42"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(
+            res.headers().get(REQUEST_ID_HEADER).unwrap(),
+            "client-id-42"
+        );
+        assert_eq!(test::read_body(res).await, "client-id-42");
+    }
+
+    #[actix_web::test]
+    async fn replaces_a_malformed_id() {
+        let app = init_app!();
+
+        for id in ["with spaces".to_string(), "x".repeat(129)] {
+            let req = test::TestRequest::get()
+                .uri("/current")
+                .insert_header((REQUEST_ID_HEADER, id.as_str()))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+
+            assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap().len(), 36);
+        }
+    }
+
+    #[actix_web::test]
+    async fn includes_the_id_in_error_bodies() {
+        let app = init_app!();
+
+        let req = test::TestRequest::get()
+            .uri("/missing")
+            .insert_header((REQUEST_ID_HEADER, "abc"))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status(), 404);
+        assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap(), "abc");
+        assert_eq!(test::read_body(res).await, "Missing (request id: abc)");
+    }
+
+    #[actix_web::test]
+    async fn includes_the_id_in_errors_of_inner_middlewares() {
+        let app = init_app!();
+
+        let req = test::TestRequest::get()
+            .uri("/rejected")
+            .insert_header((REQUEST_ID_HEADER, "abc"))
+            .to_request();
+        let res = app.call(req).await.unwrap_err().error_response();
+
+        assert_eq!(res.status(), 400);
+        assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap(), "abc");
+        assert_eq!(
+            actix_web::body::to_bytes(res.into_body()).await.unwrap(),
+            "Malformed (request id: abc)"
+        );
+    }
+}
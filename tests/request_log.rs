@@ -0,0 +1,130 @@
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use actix_web::{test, web, App, HttpResponse};
+use rust_covid_api::middleware::RequestLog;
+use serde_json::Value;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Collects the logs written by a subscriber.
+#[derive(Clone, Default)]
+struct Logs(Arc<Mutex<Vec<u8>>>);
+
+impl Logs {
+    fn lines(&self) -> Vec<Value> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+}
+
+impl io::Write for Logs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Logs {
+    type Writer = Logs;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+async fn request_logs(uri: &str) -> Vec<Value> {
+    let logs = Logs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_current_span(true)
+        .with_writer(logs.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = test::init_service(
+        App::new()
+            .wrap(RequestLog)
+            .route(
+                "/items/{id}",
+                web::get().to(|| async { HttpResponse::Ok().finish() }),
+            )
+            .route(
+                "/broken",
+                web::get().to(|| async {
+                    Err::<HttpResponse, _>(actix_web::error::ErrorInternalServerError("Boom"))
+                }),
+            ),
+    )
+    .await;
+    test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+
+    logs.lines()
+}
+
+mod request_log {
+    use super::*;
+
+    #[actix_web::test]
+    async fn logs_the_route_status_latency_and_cache_status() {
+        let logs = request_logs("/items/42").await;
+
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0]["level"], "INFO");
+        assert_eq!(logs[0]["fields"]["status"], 200);
+        assert_eq!(logs[0]["fields"]["cache"], "bypass");
+        assert!(logs[0]["fields"]["latency_ms"].is_f64());
+        assert_eq!(logs[0]["span"]["route"], "/items/{id}");
+        assert_eq!(logs[0]["span"]["path"], "/items/42");
+        assert_eq!(logs[0]["span"]["request_id"].as_str().unwrap().len(), 36);
+    }
+
+    #[actix_web::test]
+    async fn logs_server_errors_as_errors() {
+        let logs = request_logs("/broken").await;
+
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0]["level"], "ERROR");
+        assert_eq!(logs[0]["fields"]["status"], 500);
+        assert_eq!(logs[0]["fields"]["error"], "Boom");
+    }
+}
+
+mod telemetry {
+    use rust_covid_api::telemetry;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn keeps_the_request_id_in_nested_spans() {
+        let logs = Logs::default();
+        let subscriber = tracing_subscriber::registry().with(telemetry::json_layer(logs.clone()));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = test::init_service(App::new().wrap(RequestLog).route(
+            "/nested",
+            web::get().to(|| async {
+                tracing::info_span!("ingest_into_storage")
+                    .in_scope(|| tracing::warn!("Failed fetching the source API"));
+                HttpResponse::Ok().finish()
+            }),
+        ))
+        .await;
+        test::call_service(&app, test::TestRequest::get().uri("/nested").to_request()).await;
+
+        let logs = logs.lines();
+        let nested = &logs[0];
+
+        assert_eq!(nested["span"]["name"], "ingest_into_storage");
+        assert_eq!(nested["spans"][0]["name"], "http_request");
+        assert_eq!(nested["spans"][0]["request_id"].as_str().unwrap().len(), 36);
+    }
+}
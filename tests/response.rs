@@ -0,0 +1,94 @@
+use actix_web::{test, web, App};
+use rust_covid_api::routes;
+
+mod content_negotiation {
+    use super::*;
+
+    async fn get_health(accept: Option<&str>) -> actix_web::dev::ServiceResponse {
+        let app = test::init_service(
+            App::new().route("/health", web::get().to(routes::health::service_health)),
+        )
+        .await;
+
+        let mut req = test::TestRequest::get().uri("/health");
+        if let Some(accept) = accept {
+            req = req.insert_header(("Accept", accept));
+        }
+
+        test::call_service(&app, req.to_request()).await
+    }
+
+    #[actix_web::test]
+    async fn defaults_to_json() {
+        let resp = get_health(None).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "application/json"
+        );
+
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["status"], "OK");
+    }
+
+    #[actix_web::test]
+    async fn returns_messagepack_when_accepted() {
+        let resp = get_health(Some("application/msgpack")).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "application/msgpack"
+        );
+
+        let body = test::read_body(resp).await;
+        let body: serde_json::Value = rmp_serde::from_slice(&body).unwrap();
+        assert_eq!(body["status"], "OK");
+    }
+
+    #[actix_web::test]
+    async fn returns_ndjson_when_accepted() {
+        let resp = get_health(Some("application/x-ndjson")).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "application/x-ndjson"
+        );
+
+        let body = test::read_body(resp).await;
+        assert_eq!(body, "{\"status\":\"OK\"}\n");
+    }
+
+    #[actix_web::test]
+    async fn returns_csv_when_accepted() {
+        let resp = get_health(Some("text/csv")).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "text/csv; charset=utf-8"
+        );
+
+        let body = test::read_body(resp).await;
+        assert_eq!(body, "status\nOK\n");
+    }
+
+    #[actix_web::test]
+    async fn picks_the_format_with_the_highest_quality() {
+        let resp = get_health(Some("application/json;q=0.5, application/msgpack;q=0.9")).await;
+
+        assert_eq!(
+            resp.headers().get("Content-Type").unwrap(),
+            "application/msgpack"
+        );
+    }
+
+    #[actix_web::test]
+    async fn returns_406_given_unsupported_format() {
+        let resp = get_health(Some("application/xml")).await;
+
+        assert_eq!(resp.status().as_u16(), 406);
+    }
+}
@@ -0,0 +1,67 @@
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use actix_web::rt::time::sleep;
+use futures_util::future::join_all;
+use rust_covid_api::single_flight::{Flight, SingleFlight};
+
+mod single_flight {
+    use super::*;
+
+    #[actix_web::test]
+    async fn shares_the_value_among_concurrent_callers() {
+        let flight = SingleFlight::<&str, u32>::new();
+        let computations = AtomicU32::new(0);
+        let compute = || async {
+            sleep(Duration::from_millis(10)).await;
+            computations.fetch_add(1, Ordering::SeqCst) + 1
+        };
+
+        let values = join_all((0..5).map(|_| flight.run("daily", compute))).await;
+
+        assert_eq!(values, vec![1; 5]);
+        assert_eq!(computations.load(Ordering::SeqCst), 1);
+
+        // The value isn't kept once it's computed.
+        assert_eq!(flight.run("daily", compute).await, 2);
+    }
+
+    #[actix_web::test]
+    async fn computes_different_keys_separately() {
+        let flight = SingleFlight::<&str, &str>::new();
+        let compute = |key| {
+            move || async move {
+                sleep(Duration::from_millis(10)).await;
+                key
+            }
+        };
+
+        let values = join_all([
+            flight.run("daily", compute("daily")),
+            flight.run("monthly", compute("monthly")),
+        ])
+        .await;
+
+        assert_eq!(values, vec!["daily", "monthly"]);
+    }
+
+    #[actix_web::test]
+    async fn lets_followers_know_when_the_leader_gives_up() {
+        let flight = SingleFlight::<&str, u32>::new();
+
+        let leader = match flight.join("daily") {
+            Flight::Leader(leader) => leader,
+            Flight::Follower(_) => panic!("Expected to lead"),
+        };
+        let follower = match flight.join("daily") {
+            Flight::Follower(receiver) => receiver,
+            Flight::Leader(_) => panic!("Expected to follow"),
+        };
+        drop(leader);
+
+        assert!(follower.await.is_err());
+        assert!(matches!(flight.join("daily"), Flight::Leader(_)));
+    }
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use actix_web::{test, web, App};
+use actix_web_lab::middleware::from_fn;
+use rust_covid_api::{
+    routes::{daily, snapshots},
+    types::{DailyCase, DailyCases, Snapshot, SnapshotDiff},
+};
+
+mod all_snapshots {
+    use super::*;
+
+    #[actix_web::test]
+    async fn stores_fetched_data_and_reads_daily_cases_as_of_a_snapshot() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(
+                    web::scope("/daily")
+                        .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                        .service(daily::all_days),
+                )
+                .service(
+                    web::scope("/snapshots")
+                        .service(snapshots::all_snapshots)
+                        .service(snapshots::diff_snapshots),
+                ),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/daily").to_request();
+        let daily_cases: Vec<DailyCase> = test::call_and_read_body_json(&app, req).await;
+
+        let req = test::TestRequest::get().uri("/snapshots").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 200);
+        let all_snapshots: Vec<Snapshot> = test::read_body_json(resp).await;
+        let latest = all_snapshots.last().unwrap();
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/daily?as_of={}", latest.id))
+            .to_request();
+        let as_of_latest: Vec<DailyCase> = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(as_of_latest.len(), daily_cases.len());
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/snapshots/diff?from={}", latest.id))
+            .to_request();
+        let diff: SnapshotDiff = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(&diff.to, latest);
+        assert!(diff.revisions.is_empty());
+        assert_eq!((diff.added_days, diff.removed_days), (0, 0));
+    }
+
+    #[actix_web::test]
+    async fn returns_404_given_unknown_snapshot() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(
+                    web::scope("/daily")
+                        .wrap(from_fn(daily::middleware::filter_malformed_query_params))
+                        .service(daily::all_days),
+                )
+                .service(web::scope("/snapshots").service(snapshots::diff_snapshots)),
+        )
+        .await;
+
+        for uri in [
+            "/daily?as_of=1",
+            "/snapshots/diff?from=1&to=2",
+            "/snapshots/diff?from=9223372036854775807",
+            "/snapshots/diff?from=1&to=-9223372036854775808",
+        ] {
+            let req = test::TestRequest::get().uri(uri).to_request();
+            let resp = test::call_service(&app, req).await;
+
+            assert_eq!(resp.status().as_u16(), 404, "{uri}");
+        }
+    }
+}
+
+mod revisions_since {
+    use super::{DailyCase, DailyCases};
+
+    fn daily_case(day: u32, positive: i32) -> DailyCase {
+        DailyCase {
+            year: 2021,
+            month: 9,
+            day,
+            positive,
+            recovered: 10,
+            deaths: 1,
+            active: positive - 11,
+        }
+    }
+
+    #[test]
+    fn finds_revised_metrics_and_new_days() {
+        let earlier = DailyCases(vec![daily_case(1, 100), daily_case(2, 120)]);
+        let later = DailyCases(vec![
+            daily_case(1, 100),
+            daily_case(2, 150),
+            daily_case(3, 130),
+        ]);
+
+        let (revisions, added_days, removed_days) = later.revisions_since(&earlier);
+
+        assert_eq!((added_days, removed_days), (1, 0));
+        let revised = revisions
+            .iter()
+            .map(|revision| {
+                (
+                    revision.date.as_str(),
+                    revision.metric.as_str(),
+                    revision.before,
+                    revision.after,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            revised,
+            vec![
+                ("2021-09-02", "positive", 120, 150),
+                ("2021-09-02", "active", 109, 139),
+            ]
+        );
+    }
+}
+
+mod storage {
+    use std::fs;
+
+    use chrono::Utc;
+    use rust_covid_api::snapshots::SnapshotStore;
+
+    #[actix_web::test]
+    async fn reuses_the_storage_of_a_snapshot() {
+        let dir = std::env::temp_dir().join("rust-covid-api-test-snapshot-storages");
+        let _ = fs::remove_dir_all(&dir);
+        let store = SnapshotStore::new(&dir);
+        let body = fs::read("tests/fixtures/update.json").unwrap();
+        let snapshot = store.save(&body, Utc::now()).unwrap();
+
+        let storage = store.storage(snapshot.id).unwrap().unwrap();
+        let daily_cases = storage
+            .query(|storage| storage.daily_cases(None, None))
+            .await
+            .unwrap();
+
+        // Still served once the snapshot isn't read anymore.
+        fs::remove_dir_all(&dir).unwrap();
+        let storage = store.storage(snapshot.id).unwrap().unwrap();
+        let reused_daily_cases = storage
+            .query(|storage| storage.daily_cases(None, None))
+            .await
+            .unwrap();
+
+        assert!(!daily_cases.0.is_empty());
+        assert_eq!(reused_daily_cases.0.len(), daily_cases.0.len());
+        assert!(store.storage(snapshot.id + 1).unwrap().is_none());
+    }
+}
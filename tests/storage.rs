@@ -0,0 +1,102 @@
+use chrono::NaiveDate;
+use rust_covid_api::{
+    storage::Storage,
+    types::{DailyCase, DailyCases},
+};
+
+fn daily_case(day: u32, positive: i32) -> DailyCase {
+    DailyCase {
+        year: 2021,
+        month: 9,
+        day,
+        positive,
+        recovered: 10,
+        deaths: 1,
+        active: positive - 11,
+    }
+}
+
+mod upsert_daily_cases {
+    use super::*;
+
+    #[test]
+    fn inserts_new_days_and_updates_revised_ones() {
+        let mut storage = Storage::open_in_memory().unwrap();
+
+        let inserted = storage
+            .upsert_daily_cases(&DailyCases(vec![daily_case(1, 100), daily_case(2, 120)]))
+            .unwrap();
+        assert_eq!(inserted, 2);
+
+        let changed = storage
+            .upsert_daily_cases(&DailyCases(vec![
+                daily_case(1, 100),
+                daily_case(2, 150),
+                daily_case(3, 130),
+            ]))
+            .unwrap();
+        assert_eq!(changed, 2);
+
+        let stored = storage.daily_cases(None, None).unwrap();
+        let positives = stored
+            .0
+            .iter()
+            .map(|daily| daily.positive)
+            .collect::<Vec<_>>();
+        assert_eq!(positives, vec![100, 150, 130]);
+    }
+
+    #[test]
+    fn keeps_the_data_across_reopening() {
+        let path = std::env::temp_dir().join("rust-covid-api-test-storage.db");
+        let _ = std::fs::remove_file(&path);
+        let path = path.to_str().unwrap();
+
+        Storage::open(path)
+            .unwrap()
+            .upsert_daily_cases(&DailyCases(vec![daily_case(1, 100)]))
+            .unwrap();
+
+        let reopened = Storage::open(path).unwrap();
+        assert_eq!(
+            reopened.latest_date().unwrap(),
+            Some(NaiveDate::from_ymd(2021, 9, 1))
+        );
+    }
+}
+
+mod daily_cases {
+    use super::*;
+
+    #[test]
+    fn filters_by_date() {
+        let mut storage = Storage::open_in_memory().unwrap();
+        storage
+            .upsert_daily_cases(&DailyCases(
+                (1..=10).map(|day| daily_case(day, 100)).collect(),
+            ))
+            .unwrap();
+
+        let within = storage
+            .daily_cases(
+                Some(NaiveDate::from_ymd(2021, 9, 3)),
+                Some(NaiveDate::from_ymd(2021, 9, 5)),
+            )
+            .unwrap();
+        let days = within.0.iter().map(|daily| daily.day).collect::<Vec<_>>();
+        assert_eq!(days, vec![3, 4, 5]);
+
+        let since = storage
+            .daily_cases(Some(NaiveDate::from_ymd(2021, 9, 9)), None)
+            .unwrap();
+        assert_eq!(since.0.len(), 2);
+    }
+
+    #[test]
+    fn returns_nothing_given_empty_storage() {
+        let storage = Storage::open_in_memory().unwrap();
+
+        assert!(storage.daily_cases(None, None).unwrap().0.is_empty());
+        assert_eq!(storage.latest_date().unwrap(), None);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rust_covid_api::{
+    config::UpstreamConfig,
+    upstream::{self, backoff_delay, CircuitBreaker, Download, Validators, USER_AGENT},
+};
+
+/// Answer every connection with the next of `responses`, as its status line and headers,
+/// and collect the requests' headers.
+fn serve(responses: &'static [&'static str]) -> (UpstreamConfig, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let upstream = UpstreamConfig {
+        url: format!("http://{}/update.json", listener.local_addr().unwrap()),
+        retry_backoff_ms: 1,
+        ..UpstreamConfig::default()
+    };
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let received = requests.clone();
+    thread::spawn(move || {
+        for (response, stream) in responses.iter().zip(listener.incoming()) {
+            let mut stream = stream.unwrap();
+            let mut request = String::new();
+            let mut reader = BufReader::new(&stream);
+            while reader.read_line(&mut request).unwrap() > 2 {}
+            received.lock().unwrap().push(request.to_lowercase());
+
+            let body = if response.contains(" 304 ") { "" } else { "{}" };
+            write!(
+                stream,
+                "{response}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        }
+    });
+
+    (upstream, requests)
+}
+
+mod circuit_breaker {
+    use super::*;
+
+    #[test]
+    fn opens_after_consecutive_failures_until_the_cooldown_is_over() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        breaker.record_failure(now);
+        breaker.record_success();
+        breaker.record_failure(now);
+        assert_eq!(breaker.open_until(now), None);
+
+        breaker.record_failure(now);
+        assert_eq!(breaker.open_until(now), Some(now + Duration::from_secs(60)));
+        assert_eq!(breaker.open_until(now + Duration::from_secs(60)), None);
+
+        // A failure once the cooldown is over opens it again straight away.
+        let later = now + Duration::from_secs(61);
+        breaker.record_failure(later);
+        assert!(breaker.open_until(later).is_some());
+
+        breaker.record_success();
+        assert_eq!(breaker.open_until(later), None);
+    }
+}
+
+mod backoff {
+    use super::*;
+
+    #[test]
+    fn doubles_the_delay_with_jitter() {
+        let backoff = Duration::from_millis(100);
+
+        for _ in 0..100 {
+            let first = backoff_delay(backoff, 1);
+            assert!(first >= Duration::from_millis(50) && first <= backoff);
+
+            let third = backoff_delay(backoff, 3);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+        }
+    }
+}
+
+mod download {
+    use super::*;
+
+    #[actix_web::test]
+    async fn retries_transient_failures() {
+        let (upstream, requests) = serve(&[
+            "HTTP/1.1 503 Service Unavailable",
+            "HTTP/1.1 429 Too Many Requests",
+            "HTTP/1.1 200 OK",
+        ]);
+
+        let download = upstream::download(&upstream, None).await.unwrap();
+        let requests = requests.lock().unwrap();
+
+        assert!(matches!(download, Download::Modified { body, .. } if body == b"{}"));
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].contains(&format!("user-agent: {USER_AGENT}")));
+    }
+
+    #[actix_web::test]
+    async fn keeps_the_cause_of_failures() {
+        let (upstream, _) = serve(&["HTTP/1.1 404 Not Found"]);
+
+        let err = match upstream::download(&upstream, None).await {
+            Err(err) => err,
+            Ok(_) => panic!("Expected the download to fail"),
+        };
+
+        assert!(err.contains("404 Not Found"), "{err}");
+    }
+
+    #[actix_web::test]
+    async fn sends_the_validators_of_the_previous_download() {
+        let (upstream, requests) = serve(&[
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nLast-Modified: Wed, 31 Aug 2022 15:00:00 GMT",
+            "HTTP/1.1 304 Not Modified",
+        ]);
+
+        let validators = match upstream::download(&upstream, None).await.unwrap() {
+            Download::Modified { validators, .. } => validators,
+            Download::NotModified => panic!("Expected a body"),
+        };
+        assert_eq!(
+            validators,
+            Validators {
+                etag: Some("\"v1\"".to_string()),
+                last_modified: Some("Wed, 31 Aug 2022 15:00:00 GMT".to_string()),
+            }
+        );
+
+        let download = upstream::download(&upstream, Some(&validators))
+            .await
+            .unwrap();
+        let requests = requests.lock().unwrap();
+
+        assert!(matches!(download, Download::NotModified));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+        assert!(requests[1].contains("if-modified-since: wed, 31 aug 2022 15:00:00 gmt"));
+    }
+}
@@ -1,17 +1,22 @@
+mod common;
+
 use actix_web::{test, web, App};
 use rust_covid_api::{routes::yearly, types::YearlyCase};
 
 #[cfg(test)]
 mod all_years {
     use super::*;
-    use chrono::Datelike;
 
     #[actix_web::test]
     async fn returns_all_years() {
-        let current_year = chrono::Utc::now().year();
+        common::use_fixture();
+
+        let (latest_year, _, _) = common::LATEST_DATE;
         let earliest_year = 2020;
         let app = test::init_service(
-            App::new().service(web::scope("/yearly").service(yearly::all_years)),
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/yearly").service(yearly::all_years)),
         )
         .await;
 
@@ -27,7 +32,47 @@ mod all_years {
         let body: Vec<YearlyCase> = test::read_body_json(resp).await;
         assert!(!body.is_empty());
         assert_eq!(body[0].year, earliest_year);
-        assert_eq!(body.last().unwrap().year, current_year);
+        assert_eq!(body.last().unwrap().year, latest_year);
+    }
+
+    #[actix_web::test]
+    async fn bounds_the_years_even_beyond_the_supported_ones() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/yearly").service(yearly::all_years)),
+        )
+        .await;
+
+        for (uri, is_empty) in [
+            ("/yearly?since=999999", true),
+            ("/yearly?upto=-999999", true),
+            ("/yearly?since=-999999&upto=999999", false),
+        ] {
+            let req = test::TestRequest::with_uri(uri).to_request();
+            let body: Vec<YearlyCase> = test::call_and_read_body_json(&app, req).await;
+
+            assert_eq!(body.is_empty(), is_empty, "{uri}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn returns_400_given_unknown_field() {
+        common::use_fixture();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/yearly").service(yearly::all_years)),
+        )
+        .await;
+
+        let req = test::TestRequest::with_uri("/yearly?fields=vaccinated").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status().as_u16(), 400);
     }
 }
 
@@ -37,8 +82,12 @@ mod specific_year {
 
     #[actix_web::test]
     async fn returns_correct_data_given_valid_year() {
+        common::use_fixture();
+
         let app = test::init_service(
-            App::new().service(web::scope("/yearly").service(yearly::specific_year)),
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/yearly").service(yearly::specific_year)),
         )
         .await;
 
@@ -60,8 +109,12 @@ mod specific_year {
 
     #[actix_web::test]
     async fn returns_404_given_invalid_year() {
+        common::use_fixture();
+
         let app = test::init_service(
-            App::new().service(web::scope("/yearly").service(yearly::specific_year)),
+            App::new()
+                .app_data(common::storage())
+                .service(web::scope("/yearly").service(yearly::specific_year)),
         )
         .await;
 